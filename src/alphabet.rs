@@ -0,0 +1,114 @@
+use std::{
+    fmt::{self, Debug, Display},
+    hash::Hash,
+};
+
+use crate::tape::TapeValue;
+
+/// Finite set of symbols a tape cell can hold, besides being empty
+pub trait Alphabet: Clone + PartialEq + Eq + Hash + Debug + 'static {
+    /// Every symbol, in order
+    fn symbols() -> &'static [Self];
+
+    fn to_char(&self) -> char;
+
+    fn from_char(char: char) -> Option<Self> {
+        Self::symbols()
+            .iter()
+            .find(|symbol| symbol.to_char() == char)
+            .cloned()
+    }
+
+    /// Whether the symbol separates words rather than being a letter of one, `\W` rather than
+    /// `\w` in a `Pattern`. No symbol is by default.
+    fn is_separator(&self) -> bool {
+        false
+    }
+}
+
+/// Alphabet of words over `Letter` separated by its separators. Every other symbol is the letter
+/// with the same character.
+pub trait WordAlphabet: Alphabet {
+    type Letter: Alphabet;
+
+    /// Names of the machines over this alphabet in traces and input errors
+    const MACHINE_NAMES: MachineNames = MachineNames {
+        subsequence: "Subsequence",
+        guesser: "Guesser",
+        common_subsequence: "CommonSubsequence",
+    };
+
+    /// `None` for separators
+    fn letter(&self) -> Option<Self::Letter> {
+        if self.is_separator() {
+            None
+        } else {
+            Some(Self::Letter::from_char(self.to_char()).expect("Word symbol must be a letter"))
+        }
+    }
+}
+
+/// Names of `Subsequence`, `Guesser` and `CommonSubsequence` over some word alphabet
+pub struct MachineNames {
+    pub subsequence: &'static str,
+    pub guesser: &'static str,
+    pub common_subsequence: &'static str,
+}
+
+/// Character for empty cells when tapes are displayed or parsed
+pub const EMPTY_CHAR: char = '_';
+
+impl<T: Alphabet> Display for TapeValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "{}", EMPTY_CHAR),
+            Self::Value(symbol) => write!(f, "{}", symbol.to_char()),
+        }
+    }
+}
+
+impl<T: Alphabet> TapeValue<T> {
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub fn from_char(char: char) -> Option<Self> {
+        if char == EMPTY_CHAR {
+            Some(Self::Empty)
+        } else {
+            T::from_char(char).map(Self::Value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        machine::{AuxValue, MainValue},
+        test_support::Bit,
+    };
+
+    fn round_trip<T: Alphabet>() {
+        for symbol in T::symbols() {
+            assert_eq!(T::from_char(symbol.to_char()).as_ref(), Some(symbol));
+            assert_eq!(
+                TapeValue::<T>::from_char(symbol.to_char()),
+                Some(TapeValue::Value(symbol.clone()))
+            );
+        }
+        assert_eq!(
+            TapeValue::<T>::from_char(EMPTY_CHAR),
+            Some(TapeValue::Empty)
+        );
+    }
+
+    #[test]
+    fn chars() {
+        round_trip::<MainValue>();
+        round_trip::<AuxValue>();
+        round_trip::<Bit>();
+
+        assert_eq!(MainValue::from_char('#'), Some(MainValue::Hash));
+        assert_eq!(AuxValue::from_char('#'), None);
+        assert_eq!(TapeValue::Value(Bit::One).to_string(), "1");
+        assert_eq!(TapeValue::<Bit>::Empty.to_string(), "_");
+    }
+}
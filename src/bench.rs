@@ -0,0 +1,83 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    builder::TapeBuilder,
+    combinators::{LoopWhile, MainAtSeparator},
+    m1::M1,
+    machine::{AuxValue, MainValue, TuringMachine},
+    tape::{Tape, TapeValue},
+};
+
+/// Tapes for checking that `a` is a subsequence of each of `words` words of `length` a's, in the
+/// layout `M3` leaves them in after guessing
+fn tapes(words: usize, length: usize) -> (Tape<MainValue>, Tape<AuxValue>) {
+    let word = "a".repeat(length);
+    (
+        TapeBuilder::new()
+            .head()
+            .separator()
+            .words(&vec![word.as_str(); words])
+            .blank()
+            .build(),
+        TapeBuilder::new().head().blank().word("a").blank().build(),
+    )
+}
+
+/// Runs M1 over every word the way `M3` used to, copying the tapes into and out of each run
+fn run_cloned(main_tape: &mut Tape<MainValue>, aux_tape: &mut Tape<AuxValue>) -> bool {
+    loop {
+        let mut m1 = M1::new(main_tape.clone(), aux_tape.clone());
+        let output = m1.run();
+        let destroy = m1.destroy();
+        *main_tape = destroy.main_tape().clone();
+        *aux_tape = destroy.aux_tape().clone();
+
+        if !output {
+            return false;
+        }
+        if main_tape.read() != TapeValue::Value(MainValue::Hash) {
+            return true;
+        }
+    }
+}
+
+fn time(
+    runs: u32,
+    words: usize,
+    length: usize,
+    run: fn(&mut Tape<MainValue>, &mut Tape<AuxValue>) -> bool,
+) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..runs {
+        let (mut main_tape, mut aux_tape) = tapes(words, length);
+        let start = Instant::now();
+        assert!(run(&mut main_tape, &mut aux_tape));
+        total += start.elapsed();
+    }
+    total / runs
+}
+
+/// Compares running M1 over every word on cloned tapes with running it on borrowed tapes. Both
+/// take time linear in the words for each run of M1, but cloning also copies the whole tape for
+/// each, so its time grows with the square of the words and only falls behind on long tapes.
+pub fn bench(max_words: usize, length: usize, runs: u32) {
+    println!("words\tcloned\tin place\tratio");
+    let mut words = 1;
+    while words <= max_words {
+        let cloned = time(runs, words, length, run_cloned);
+        let in_place = time(
+            runs,
+            words,
+            length,
+            LoopWhile::<M1, MainAtSeparator>::run_on,
+        );
+        println!(
+            "{}\t{:?}\t{:?}\t{:.1}",
+            words,
+            cloned,
+            in_place,
+            cloned.as_secs_f64() / in_place.as_secs_f64()
+        );
+        words *= 2;
+    }
+}
@@ -0,0 +1,194 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    alphabet::{Alphabet, WordAlphabet},
+    tape::{Tape, TapeConstructor, TapeValue},
+};
+
+/// Builds a tape cell by cell from the left, for example a blank head followed by `ab#ba` with
+/// `TapeBuilder::new().head().blank().words(&["ab", "ba"]).build()`
+pub struct TapeBuilder<T: Alphabet> {
+    data: Vec<TapeConstructor<T>>,
+    head_next: bool,
+    steps_ran: Option<Rc<RefCell<u64>>>,
+}
+
+impl<T: Alphabet> Default for TapeBuilder<T> {
+    fn default() -> Self {
+        Self {
+            data: vec![],
+            head_next: false,
+            steps_ran: None,
+        }
+    }
+}
+
+impl<T: Alphabet> TapeBuilder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the steps of the tape on `steps_ran`
+    #[must_use]
+    pub fn steps_ran(mut self, steps_ran: Option<Rc<RefCell<u64>>>) -> Self {
+        self.steps_ran = steps_ran;
+        self
+    }
+
+    /// Puts the head on the next cell
+    #[must_use]
+    pub fn head(mut self) -> Self {
+        assert!(
+            !self.head_next
+                && self
+                    .data
+                    .iter()
+                    .all(|cell| matches!(cell, TapeConstructor::Value(_))),
+            "Tape can only have one head"
+        );
+        self.head_next = true;
+        self
+    }
+
+    #[must_use]
+    pub fn cell(mut self, value: TapeValue<T>) -> Self {
+        self.data.push(if self.head_next {
+            TapeConstructor::Head(value)
+        } else {
+            TapeConstructor::Value(value)
+        });
+        self.head_next = false;
+        self
+    }
+
+    #[must_use]
+    pub fn symbol(self, symbol: T) -> Self {
+        self.cell(TapeValue::Value(symbol))
+    }
+
+    #[must_use]
+    pub fn blank(self) -> Self {
+        self.cell(TapeValue::Empty)
+    }
+
+    #[must_use]
+    #[cfg(test)]
+    pub fn blanks(self, count: usize) -> Self {
+        (0..count).fold(self, |builder, _| builder.blank())
+    }
+
+    /// One cell per character of `word`, which must be a symbol of the alphabet
+    #[must_use]
+    pub fn word(self, word: &str) -> Self {
+        word.chars().fold(self, |builder, char| {
+            let symbol = T::from_char(char)
+                .unwrap_or_else(|| panic!("{} is not a symbol of the alphabet", char));
+            builder.symbol(symbol)
+        })
+    }
+
+    /// One cell per character of `cells`, `_` for an empty cell
+    #[must_use]
+    #[cfg(test)]
+    pub fn cells(self, cells: &str) -> Self {
+        cells.chars().fold(self, |builder, char| {
+            let cell = TapeValue::from_char(char)
+                .unwrap_or_else(|| panic!("{} is not a symbol of the alphabet", char));
+            builder.cell(cell)
+        })
+    }
+
+    /// First separator of the alphabet
+    #[must_use]
+    pub fn separator(self) -> Self
+    where
+        T: WordAlphabet,
+    {
+        let separator = T::symbols()
+            .iter()
+            .find(|symbol| symbol.is_separator())
+            .expect("Alphabet must have a separator")
+            .clone();
+        self.symbol(separator)
+    }
+
+    /// `words` with a separator between each
+    #[must_use]
+    pub fn words(self, words: &[&str]) -> Self
+    where
+        T: WordAlphabet,
+    {
+        words
+            .iter()
+            .enumerate()
+            .fold(self, |builder, (index, word)| {
+                if index == 0 {
+                    builder
+                } else {
+                    builder.separator()
+                }
+                .word(word)
+            })
+    }
+
+    pub fn build(self) -> Tape<T> {
+        assert!(!self.head_next, "Head must be on a cell");
+        Tape::new(self.data, self.steps_ran)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        m1::M1,
+        m2::M2,
+        m3::M3,
+        machine::{AuxValue, MainValue, TuringMachine},
+    };
+
+    #[test]
+    fn build() {
+        let tape: Tape<MainValue> = TapeBuilder::new()
+            .blanks(2)
+            .head()
+            .separator()
+            .words(&["ab", "", "b"])
+            .blank()
+            .build();
+        assert_eq!(tape.head(), 2);
+        assert_eq!(
+            tape.data()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<String>(),
+            "__#ab##b_"
+        );
+
+        let tape: Tape<MainValue> = TapeBuilder::new().cells("a_").head().cells("#").build();
+        assert_eq!(tape.head(), 2);
+        assert_eq!(tape.data()[1], TapeValue::Empty);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tape can only have one head")]
+    fn two_heads() {
+        let _ = TapeBuilder::<AuxValue>::new().head().blank().head();
+    }
+
+    #[test]
+    #[should_panic(expected = "c is not a symbol of the alphabet")]
+    fn not_a_symbol() {
+        let _ = TapeBuilder::<AuxValue>::new().word("abc");
+    }
+
+    #[test]
+    fn input_for() {
+        let (main_tape, aux_tape) = M1::input_for("abba", "ab", None);
+        assert!(M1::new(main_tape, aux_tape).run());
+        let (main_tape, aux_tape) = M2::input_for("ab", None);
+        M2::new(main_tape, aux_tape);
+        let (main_tape, aux_tape) = M3::input_for(&["ab", "ba", "abb"], None);
+        M3::new(main_tape, aux_tape);
+    }
+}
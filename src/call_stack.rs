@@ -0,0 +1,171 @@
+use log::trace;
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+struct Frame {
+    name: &'static str,
+    invocation: u64,
+    state: Option<u32>,
+    steps_ran: Option<Rc<RefCell<u64>>>,
+    steps_at_entry: u64,
+}
+
+impl Frame {
+    fn steps(&self) -> u64 {
+        self.steps_ran
+            .as_ref()
+            .map_or(0, |steps_ran| *steps_ran.borrow() - self.steps_at_entry)
+    }
+}
+
+/// Steps consumed by every invocation of a machine during a run
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Summary {
+    pub invocations: u64,
+    pub steps: u64,
+}
+
+#[derive(Default)]
+struct CallStack {
+    frames: Vec<Frame>,
+    summaries: BTreeMap<&'static str, Summary>,
+}
+
+impl CallStack {
+    /// For example `M3:1 > M1#4:8` for the fourth run of M1, in state 8, called by M3 in state 1
+    fn path(&self) -> String {
+        self.frames
+            .iter()
+            .enumerate()
+            .map(|(depth, frame)| {
+                let mut segment = frame.name.to_owned();
+                if depth > 0 {
+                    segment.push_str(&format!("#{}", frame.invocation));
+                }
+                if let Some(state) = frame.state {
+                    segment.push_str(&format!(":{}", state));
+                }
+                segment
+            })
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+}
+
+thread_local! {
+    static CALL_STACK: RefCell<CallStack> = RefCell::new(CallStack::default());
+}
+
+/// Frame on the call stack for a running machine, popped when dropped
+pub struct Invocation;
+
+/// Pushes a frame for the machine `name`, counting steps from `steps_ran` if given. Entering a
+/// machine with an empty call stack starts a new run and clears the summary of the last one.
+pub fn enter(name: &'static str, steps_ran: Option<Rc<RefCell<u64>>>) -> Invocation {
+    CALL_STACK.with(|call_stack| {
+        let mut call_stack = call_stack.borrow_mut();
+        if call_stack.frames.is_empty() {
+            call_stack.summaries.clear();
+        }
+        let summary = call_stack.summaries.entry(name).or_default();
+        summary.invocations += 1;
+        let invocation = summary.invocations;
+        let steps_at_entry = steps_ran
+            .as_ref()
+            .map_or(0, |steps_ran| *steps_ran.borrow());
+        call_stack.frames.push(Frame {
+            name,
+            invocation,
+            state: None,
+            steps_ran,
+            steps_at_entry,
+        });
+    });
+    Invocation
+}
+
+impl Drop for Invocation {
+    fn drop(&mut self) {
+        CALL_STACK.with(|call_stack| {
+            let mut call_stack = call_stack.borrow_mut();
+            let steps = call_stack
+                .frames
+                .last()
+                .expect("Call stack must not be empty")
+                .steps();
+            trace!("{} done in {} steps", call_stack.path(), steps);
+            let frame = call_stack.frames.pop().unwrap();
+            call_stack.summaries.entry(frame.name).or_default().steps += steps;
+        });
+    }
+}
+
+/// Moves the innermost machine to `state` and traces the call path
+pub fn state(state: u32) {
+    CALL_STACK.with(|call_stack| {
+        let mut call_stack = call_stack.borrow_mut();
+        if let Some(frame) = call_stack.frames.last_mut() {
+            frame.state = Some(state);
+        }
+        trace!("{}", call_stack.path());
+    });
+}
+
+/// Innermost running machine and its state, if any machine is running
+pub fn current() -> Option<(&'static str, Option<u32>)> {
+    CALL_STACK.with(|call_stack| {
+        call_stack
+            .borrow()
+            .frames
+            .last()
+            .map(|frame| (frame.name, frame.state))
+    })
+}
+
+/// Invocations and steps of each machine during the current or last run
+pub fn summary() -> BTreeMap<&'static str, Summary> {
+    CALL_STACK.with(|call_stack| call_stack.borrow().summaries.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_and_summary() {
+        let steps_ran = Rc::new(RefCell::new(0));
+        {
+            let _outer = enter("Outer", Some(Rc::clone(&steps_ran)));
+            state(1);
+            for _ in 0..3 {
+                let _inner = enter("Inner", Some(Rc::clone(&steps_ran)));
+                state(8);
+                *steps_ran.borrow_mut() += 2;
+            }
+            let _inner = enter("Inner", Some(Rc::clone(&steps_ran)));
+            state(4);
+            assert_eq!(
+                CALL_STACK.with(|call_stack| call_stack.borrow().path()),
+                "Outer:1 > Inner#4:4"
+            );
+            *steps_ran.borrow_mut() += 1;
+            assert_eq!(current(), Some(("Inner", Some(4))));
+        }
+        assert_eq!(current(), None);
+
+        let summary = summary();
+        assert_eq!(
+            summary["Outer"],
+            Summary {
+                invocations: 1,
+                steps: 7
+            }
+        );
+        assert_eq!(
+            summary["Inner"],
+            Summary {
+                invocations: 4,
+                steps: 7
+            }
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+/// Source of nondeterministic choices
+pub trait Chooser {
+    /// Index of one of `count` options. A choice between two options is a choice among 2.
+    fn choose_among(&mut self, count: usize) -> usize;
+}
+
+/// Picks every option uniformly at random
+pub struct RandomChooser;
+
+impl Chooser for RandomChooser {
+    fn choose_among(&mut self, count: usize) -> usize {
+        assert!(count > 0, "Random chooser must have an option to choose");
+        rand::thread_rng().gen_range(0..count)
+    }
+}
+
+/// Picks every option uniformly at random from a seeded generator, so the same seed makes the same
+/// choices
+pub struct SeededChooser {
+    rng: StdRng,
+}
+
+impl SeededChooser {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Chooser for SeededChooser {
+    fn choose_among(&mut self, count: usize) -> usize {
+        assert!(count > 0, "Seeded chooser must have an option to choose");
+        self.rng.gen_range(0..count)
+    }
+}
+
+/// Replays a fixed sequence of choices
+pub struct ScriptedChooser {
+    choices: VecDeque<usize>,
+}
+
+impl ScriptedChooser {
+    pub fn new(choices: Vec<usize>) -> Self {
+        Self {
+            choices: choices.into(),
+        }
+    }
+}
+
+impl Chooser for ScriptedChooser {
+    fn choose_among(&mut self, count: usize) -> usize {
+        let choice = self
+            .choices
+            .pop_front()
+            .expect("Scripted chooser ran out of choices");
+        assert!(
+            choice < count,
+            "Scripted choice must be less than {}",
+            count
+        );
+        choice
+    }
+}
+
+/// Walks every branch of a computation depth first, one run per branch, see `explore`
+#[derive(Default)]
+pub struct ExhaustiveChooser {
+    /// Choice and option count at each choice of the current branch
+    branch: Vec<(usize, usize)>,
+    position: usize,
+}
+
+impl ExhaustiveChooser {
+    /// Moves to the next branch, `false` if every branch has been run
+    pub fn next_branch(&mut self) -> bool {
+        self.branch.truncate(self.position);
+        self.position = 0;
+        while let Some((choice, count)) = self.branch.pop() {
+            if choice + 1 < count {
+                self.branch.push((choice + 1, count));
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Choices made on the current branch
+    pub fn choices(&self) -> Vec<usize> {
+        self.branch[..self.position]
+            .iter()
+            .map(|(choice, _)| *choice)
+            .collect()
+    }
+}
+
+impl Chooser for ExhaustiveChooser {
+    fn choose_among(&mut self, count: usize) -> usize {
+        assert!(
+            count > 0,
+            "Exhaustive chooser must have an option to choose"
+        );
+        if self.position == self.branch.len() {
+            self.branch.push((0, count));
+        }
+        let (choice, branch_count) = self.branch[self.position];
+        assert_eq!(
+            count, branch_count,
+            "Branch must be replayed with the same choices"
+        );
+        self.position += 1;
+        choice
+    }
+}
+
+thread_local! {
+    static CHOOSER: RefCell<Rc<RefCell<dyn Chooser>>> =
+        RefCell::new(Rc::new(RefCell::new(RandomChooser)));
+}
+
+/// Restores the previous chooser when dropped
+struct Restore(Option<Rc<RefCell<dyn Chooser>>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take().unwrap();
+        CHOOSER.with(|chooser| *chooser.borrow_mut() = previous);
+    }
+}
+
+/// Runs `f` with `chooser` making every choice
+pub fn with_chooser<R>(chooser: Rc<RefCell<dyn Chooser>>, f: impl FnOnce() -> R) -> R {
+    let _restore = Restore(Some(CHOOSER.with(|current| current.replace(chooser))));
+    f()
+}
+
+fn current() -> Rc<RefCell<dyn Chooser>> {
+    CHOOSER.with(|chooser| Rc::clone(&chooser.borrow()))
+}
+
+/// Index of one of `count` options from the current chooser
+pub fn choose_among(count: usize) -> usize {
+    let chooser = current();
+    let choice = chooser.borrow_mut().choose_among(count);
+    choice
+}
+
+/// Outcome of running every branch
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Exploration {
+    pub branches: u64,
+    /// Choices of the first accepting branch
+    pub accepted: Option<Vec<usize>>,
+}
+
+/// Runs `run` once per branch of its choices, stopping at the first accepting branch if
+/// `stop_on_accept`
+pub fn explore(stop_on_accept: bool, mut run: impl FnMut() -> bool) -> Exploration {
+    let chooser = Rc::new(RefCell::new(ExhaustiveChooser::default()));
+    let mut exploration = Exploration {
+        branches: 0,
+        accepted: None,
+    };
+    loop {
+        let accepted = with_chooser(chooser.clone(), &mut run);
+        exploration.branches += 1;
+        if accepted && exploration.accepted.is_none() {
+            exploration.accepted = Some(chooser.borrow().choices());
+            if stop_on_accept {
+                return exploration;
+            }
+        }
+        if !chooser.borrow_mut().next_branch() {
+            return exploration;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted() {
+        let chooser = Rc::new(RefCell::new(ScriptedChooser::new(vec![2, 0, 1])));
+        let choices = with_chooser(chooser, || {
+            (choose_among(3), choose_among(4), choose_among(2))
+        });
+        assert_eq!(choices, (2, 0, 1));
+    }
+
+    #[test]
+    fn seeded() {
+        let choices = || {
+            let chooser = Rc::new(RefCell::new(SeededChooser::new(7)));
+            with_chooser(chooser, || {
+                (0..20).map(|_| choose_among(4)).collect::<Vec<_>>()
+            })
+        };
+        assert_eq!(choices(), choices());
+    }
+
+    #[test]
+    #[should_panic(expected = "Random chooser must have an option to choose")]
+    fn no_options() {
+        RandomChooser.choose_among(0);
+    }
+
+    #[test]
+    fn exhaustive() {
+        let mut runs = vec![];
+        let exploration = explore(false, || {
+            let first = choose_among(3);
+            let second = if first == 1 {
+                Some(choose_among(2))
+            } else {
+                None
+            };
+            runs.push((first, second));
+            false
+        });
+        assert_eq!(exploration.branches, 4);
+        assert_eq!(exploration.accepted, None);
+        assert_eq!(runs, vec![(0, None), (1, Some(0)), (1, Some(1)), (2, None)]);
+
+        let exploration = explore(true, || choose_among(5) == 3);
+        assert_eq!(exploration.branches, 4);
+        assert_eq!(exploration.accepted, Some(vec![3]));
+    }
+}
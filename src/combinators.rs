@@ -0,0 +1,196 @@
+use std::marker::PhantomData;
+
+use crate::{
+    alphabet::{Alphabet, WordAlphabet},
+    call_stack,
+    machine::{DestroyOutput, TuringMachine},
+    tape::{Tape, TapeValue},
+};
+
+/// Condition on the tapes between machine runs
+pub trait TapePredicate<M: Alphabet, A: Alphabet> {
+    fn test(main_tape: &Tape<M>, aux_tape: &Tape<A>) -> bool;
+}
+
+/// Main tape head reads a separator, such as a hash
+pub struct MainAtSeparator;
+
+impl<M: WordAlphabet> TapePredicate<M, M::Letter> for MainAtSeparator {
+    fn test(main_tape: &Tape<M>, _aux_tape: &Tape<M::Letter>) -> bool {
+        matches!(main_tape.read(), TapeValue::Value(symbol) if symbol.is_separator())
+    }
+}
+
+/// Defines a machine composed of the machine `A` and others, all sharing the alphabets of `A`. Its
+/// runs have a frame of their own on the call stack, named after it, around those of its machines.
+macro_rules! combinator {
+    (
+        $(#[$meta:meta])*
+        $name:ident<A $(, $param:ident: $bound:path)*>,
+        |$main_tape:ident, $aux_tape:ident| $run:block
+    ) => {
+        $(#[$meta])*
+        pub struct $name<A: TuringMachine $(, $param: $bound)*> {
+            main_tape: Tape<A::Main>,
+            aux_tape: Tape<A::Aux>,
+            machines: PhantomData<(A, $($param,)*)>,
+        }
+
+        impl<A: TuringMachine $(, $param: $bound)*> TuringMachine for $name<A $(, $param)*> {
+            type Main = A::Main;
+            type Aux = A::Aux;
+
+            fn new(main_tape: Tape<A::Main>, aux_tape: Tape<A::Aux>) -> Self {
+                Self {
+                    main_tape,
+                    aux_tape,
+                    machines: PhantomData,
+                }
+            }
+
+            fn run(&mut self) -> bool {
+                let _invocation =
+                    call_stack::enter(stringify!($name), self.main_tape.steps_ran_counter());
+                let $main_tape = &mut self.main_tape;
+                let $aux_tape = &mut self.aux_tape;
+                $run
+            }
+
+            fn destroy(self) -> DestroyOutput<A::Main, A::Aux> {
+                DestroyOutput::new(self.main_tape, self.aux_tape)
+            }
+        }
+    };
+}
+
+combinator!(
+    /// Runs `A` then `B`, accepting if `B` accepts
+    #[allow(dead_code)]
+    Then<A, B: TuringMachine<Main = A::Main, Aux = A::Aux>>,
+    |main_tape, aux_tape| {
+        A::run_on(main_tape, aux_tape);
+        B::run_on(main_tape, aux_tape)
+    }
+);
+
+combinator!(
+    /// Runs `A` until it rejects or `P` no longer holds afterwards, accepting in the latter case
+    LoopWhile<A, P: TapePredicate<A::Main, A::Aux>>,
+    |main_tape, aux_tape| {
+        loop {
+            if !A::run_on(main_tape, aux_tape) {
+                return false;
+            }
+            if !P::test(main_tape, aux_tape) {
+                return true;
+            }
+        }
+    }
+);
+
+combinator!(
+    /// Runs `A`, then `T` if it accepts or `E` if it rejects
+    #[allow(dead_code)]
+    IfAccept<
+        A,
+        T: TuringMachine<Main = A::Main, Aux = A::Aux>,
+        E: TuringMachine<Main = A::Main, Aux = A::Aux>
+    >,
+    |main_tape, aux_tape| {
+        if A::run_on(main_tape, aux_tape) {
+            T::run_on(main_tape, aux_tape)
+        } else {
+            E::run_on(main_tape, aux_tape)
+        }
+    }
+);
+
+combinator!(
+    /// Runs `A`, accepting if it rejects
+    #[allow(dead_code)]
+    Not<A>,
+    |main_tape, aux_tape| { !A::run_on(main_tape, aux_tape) }
+);
+
+combinator!(
+    /// Runs `A`, then `B` if `A` accepts
+    #[allow(dead_code)]
+    And<A, B: TuringMachine<Main = A::Main, Aux = A::Aux>>,
+    |main_tape, aux_tape| {
+        A::run_on(main_tape, aux_tape) && B::run_on(main_tape, aux_tape)
+    }
+);
+
+combinator!(
+    /// Runs `A`, then `B` if `A` rejects
+    #[allow(dead_code)]
+    Or<A, B: TuringMachine<Main = A::Main, Aux = A::Aux>>,
+    |main_tape, aux_tape| {
+        A::run_on(main_tape, aux_tape) || B::run_on(main_tape, aux_tape)
+    }
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::TapeBuilder,
+        m1::M1,
+        machine::{AuxValue, MainValue},
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    fn run<M: TuringMachine<Main = MainValue, Aux = AuxValue>>(
+        main: &str,
+        aux: &str,
+    ) -> (bool, u64) {
+        let steps_ran = Rc::new(RefCell::new(0));
+        // Main tape `#main_` with the head on the hash, and aux tape `_aux_`, as `M1` expects
+        let main_tape = TapeBuilder::new()
+            .steps_ran(Some(Rc::clone(&steps_ran)))
+            .head()
+            .separator()
+            .words(&main.split('#').collect::<Vec<_>>())
+            .blank()
+            .build();
+        let aux_tape = TapeBuilder::new()
+            .steps_ran(Some(Rc::clone(&steps_ran)))
+            .head()
+            .blank()
+            .word(aux)
+            .blank()
+            .build();
+        let mut machine = M::new(main_tape, aux_tape);
+        let output = machine.run();
+        let steps_ran = *steps_ran.borrow();
+        (output, steps_ran)
+    }
+
+    #[test]
+    fn not() {
+        assert_eq!(run::<Not<M1>>("abb", "ab").0, !run::<M1>("abb", "ab").0);
+        assert_eq!(run::<Not<M1>>("abb", "ba").0, !run::<M1>("abb", "ba").0);
+        assert_eq!(run::<Not<M1>>("abb", "ab").1, run::<M1>("abb", "ab").1);
+    }
+
+    #[test]
+    fn loop_while() {
+        // Each run of M1 leaves the main tape head on the hash after the word it checked
+        assert!(run::<LoopWhile<M1, MainAtSeparator>>("ab#ba#bab", "b").0);
+        assert!(!run::<LoopWhile<M1, MainAtSeparator>>("ab#aa#bab", "b").0);
+        assert!(!run::<LoopWhile<M1, MainAtSeparator>>("ab#ba#aaa", "b").0);
+    }
+
+    #[test]
+    fn and_or_if_accept() {
+        type Twice = And<M1, M1>;
+        assert!(run::<Twice>("ab#ba", "b").0);
+        assert!(!run::<Twice>("ab#aa", "b").0);
+        assert!(run::<Or<M1, M1>>("aa#ba", "b").0);
+        assert!(!run::<Or<M1, M1>>("aa#aa", "b").0);
+        assert!(run::<IfAccept<M1, M1, Not<M1>>>("ab#ba", "b").0);
+        assert!(run::<IfAccept<M1, M1, Not<M1>>>("aa#aa", "b").0);
+        assert!(!run::<IfAccept<M1, M1, Not<M1>>>("ab#aa", "b").0);
+        assert!(run::<Then<M1, M1>>("aa#ba", "b").0);
+    }
+}
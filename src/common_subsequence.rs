@@ -0,0 +1,224 @@
+use std::collections::HashSet;
+
+use crate::{
+    alphabet::{Alphabet, WordAlphabet},
+    call_stack,
+    combinators::{LoopWhile, MainAtSeparator},
+    generate::Oracle,
+    guesser::Guesser,
+    machine::{run_on_with, DestroyOutput, TuringMachine, Verifier},
+    pattern::{InputShape, Walk},
+    subsequence::Subsequence,
+    tape::Tape,
+};
+
+/// Guesses a word as long as the first word on the main tape and accepts if it is a subsequence of
+/// every other word, over any word alphabet. `M3` is this over `MainValue`.
+///
+/// The guess is made by `Guesser`, and each word is then checked by a run of `Subsequence` from
+/// the separator before it, see `Check`. Its states are 0 while guessing, 1 while checking, and 2
+/// and 3 once it rejects or accepts.
+pub struct CommonSubsequence<M: WordAlphabet> {
+    main_tape: Tape<M>,
+    aux_tape: Tape<M::Letter>,
+    certificate_tape: Option<Tape<M::Letter>>,
+    substring: Option<String>,
+}
+
+/// Runs `Subsequence` on each word after the main tape head, while it accepts
+pub type Check<M> = LoopWhile<Subsequence<M>, MainAtSeparator>;
+
+impl<M: WordAlphabet> TuringMachine for CommonSubsequence<M> {
+    type Main = M;
+    type Aux = M::Letter;
+
+    fn new(main_tape: Tape<M>, aux_tape: Tape<M::Letter>) -> Self {
+        Self::check_input(&main_tape, &aux_tape);
+
+        Self {
+            main_tape,
+            aux_tape,
+            certificate_tape: None,
+            substring: None,
+        }
+    }
+
+    fn run(&mut self) -> bool {
+        let _invocation = call_stack::enter(Self::NAME, self.main_tape.steps_ran_counter());
+        call_stack::state(0);
+        let (main_tape, aux_tape) = (&mut self.main_tape, &mut self.aux_tape);
+        let guessed = match self.certificate_tape.take() {
+            Some(certificate_tape) => run_on_with(main_tape, aux_tape, |main_tape, aux_tape| {
+                Guesser::with_certificate(main_tape, aux_tape, certificate_tape)
+            }),
+            None => Guesser::<M>::run_on(main_tape, aux_tape),
+        };
+        if !guessed {
+            call_stack::state(2);
+            return false;
+        }
+
+        // Guessing leaves the aux head on the empty cell before the word it guessed. Reading it
+        // back counts the steps of walking a copy of the aux tape over it and the empty cell after.
+        let substring = aux_tape.word_at(aux_tape.head() + 1);
+        aux_tape.count_walk(substring.len() + 1);
+        self.substring = Some(substring.to_string());
+        call_stack::state(1);
+        let output = Check::<M>::run_on(main_tape, aux_tape);
+        call_stack::state(if output { 3 } else { 2 });
+        output
+    }
+
+    fn destroy(self) -> DestroyOutput<M, M::Letter> {
+        DestroyOutput::new(self.main_tape, self.aux_tape)
+    }
+}
+
+/// Words separated by separators after an empty head, and a blank aux tape
+impl<M: WordAlphabet> InputShape for CommonSubsequence<M> {
+    const NAME: &'static str = M::MACHINE_NAMES.common_subsequence;
+    const MAIN_SHAPE: &'static str = r"_* [_] \w+ (\W \w+)* _*";
+    const AUX_SHAPE: &'static str = "_* [_] _*";
+    const MAIN_WALK: Walk = Walk::Tape;
+    const AUX_WALK: Walk = Walk::Still;
+}
+
+/// Accepts if some word as long as the first is a subsequence of every other word, see
+/// `has_common_subsequence`
+impl<M: WordAlphabet> Oracle for CommonSubsequence<M> {
+    fn accepts(main_tape: &Tape<M>, _aux_tape: &Tape<M::Letter>) -> bool {
+        let mut words = main_tape
+            .words_from(main_tape.head() + 1)
+            .map(|word| word.letters());
+        let length = words.next().map_or(0, |word| word.len());
+        let words: Vec<_> = words.collect();
+        has_common_subsequence(
+            &words,
+            M::Letter::symbols(),
+            length,
+            vec![0; words.len()],
+            &mut HashSet::new(),
+        )
+    }
+}
+
+/// Whether some word of `length` more letters is a subsequence of each of `words` after the
+/// letters up to its position in `positions`. Each letter is matched as early as it can be in every
+/// word, and only while each word has room left for the rest, so each word only has as many
+/// positions as letters it has beyond `length`. Searching is linear when no word is longer than
+/// `length`, and polynomial in their lengths for any fixed number of words.
+fn has_common_subsequence<T: PartialEq>(
+    words: &[Vec<T>],
+    letters: &[T],
+    length: usize,
+    positions: Vec<usize>,
+    dead_ends: &mut HashSet<(usize, Vec<usize>)>,
+) -> bool {
+    if length == 0 {
+        return true;
+    }
+    if dead_ends.contains(&(length, positions.clone())) {
+        return false;
+    }
+    for letter in letters {
+        let next = words
+            .iter()
+            .zip(&positions)
+            .map(|(word, &position)| {
+                let after = position + word[position..].iter().position(|l| l == letter)? + 1;
+                (word.len() - after >= length - 1).then_some(after)
+            })
+            .collect();
+        if let Some(next) = next {
+            if has_common_subsequence(words, letters, length - 1, next, dead_ends) {
+                return true;
+            }
+        }
+    }
+    dead_ends.insert((length, positions));
+    false
+}
+
+/// Deterministic variant that guesses the substring from the certificate tape, see `Guesser`
+impl<M: WordAlphabet> Verifier for CommonSubsequence<M> {
+    fn with_certificate(
+        main_tape: Tape<M>,
+        aux_tape: Tape<M::Letter>,
+        certificate_tape: Tape<M::Letter>,
+    ) -> Self {
+        let mut common_subsequence = Self::new(main_tape, aux_tape);
+        common_subsequence.certificate_tape = Some(certificate_tape);
+        common_subsequence
+    }
+}
+
+impl<M: WordAlphabet> CommonSubsequence<M> {
+    /// Word guessed by the last run, `None` if it stopped before finishing its guess, as when a
+    /// certificate is the wrong length
+    pub fn substring(&self) -> Option<&str> {
+        self.substring.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alphabet::Alphabet,
+        builder::TapeBuilder,
+        machine::certificate_tape,
+        test_support::{Base, Strand},
+    };
+
+    /// Tapes for `words` as `M3::input_for` lays them out
+    fn input(words: &[&str]) -> (Tape<Strand>, Tape<Base>) {
+        (
+            TapeBuilder::new().head().blank().words(words).build(),
+            TapeBuilder::new().head().blank().build(),
+        )
+    }
+
+    fn certificate(word: &str) -> Vec<Base> {
+        word.chars()
+            .map(|char| Alphabet::from_char(char).unwrap())
+            .collect()
+    }
+
+    fn accepts(words: &[&str], certificate: &str) -> bool {
+        let (main_tape, aux_tape) = input(words);
+        CommonSubsequence::accepts_certificate(
+            main_tape,
+            aux_tape,
+            certificate_tape(&self::certificate(certificate), None),
+        )
+    }
+
+    #[test]
+    fn bases() {
+        assert!(accepts(&["gtc", "gattaca", "tgtcc"], "gtc"));
+        assert!(!accepts(&["gtc", "gattaca", "tgcc"], "gtc"));
+
+        let (main_tape, aux_tape) = input(&["gtc", "gattaca", "tgtcc"]);
+        let mut machine = CommonSubsequence::new(main_tape, aux_tape);
+        let _ = machine.run();
+        assert_eq!(machine.substring().map(str::len), Some(3));
+
+        // Rejecting after the guess still records it, unlike a certificate too short to finish one
+        let (main_tape, aux_tape) = input(&["gtc", "gattaca", "tgcc"]);
+        let mut machine = CommonSubsequence::with_certificate(
+            main_tape,
+            aux_tape,
+            certificate_tape(&certificate("gtc"), None),
+        );
+        assert!(!machine.run());
+        assert_eq!(machine.substring(), Some("gtc"));
+        let (main_tape, aux_tape) = input(&["gtc", "gattaca"]);
+        let mut machine = CommonSubsequence::with_certificate(
+            main_tape,
+            aux_tape,
+            certificate_tape(&certificate("gt"), None),
+        );
+        assert!(!machine.run());
+        assert_eq!(machine.substring(), None);
+    }
+}
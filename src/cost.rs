@@ -0,0 +1,147 @@
+use std::{cell::RefCell, rc::Rc};
+
+/// Primitive operation on a tape
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Operation {
+    Read,
+    Write,
+    /// Moving left or right, staying is not an operation
+    Move,
+}
+
+/// Convention for how many steps a run takes
+pub trait CostModel {
+    fn name(&self) -> &'static str;
+    /// Cost of an operation made on its own, outside of a synchronous transition
+    fn operation(&self, operation: Operation) -> u64;
+    /// Cost of a synchronous transition whose operations would cost `operations` on their own
+    fn transition(&self, operations: u64) -> u64;
+    /// Cost of reading every head and halting rather than making a transition
+    fn halt(&self, operations: u64) -> u64 {
+        operations
+    }
+}
+
+/// Counts only moves, as when measuring how far heads travel
+pub struct MovesOnly;
+
+impl CostModel for MovesOnly {
+    fn name(&self) -> &'static str {
+        "moves only"
+    }
+
+    fn operation(&self, operation: Operation) -> u64 {
+        u64::from(operation == Operation::Move)
+    }
+
+    fn transition(&self, operations: u64) -> u64 {
+        operations
+    }
+}
+
+/// Counts every read, write and move on every tape, the default
+pub struct PrimitiveOps;
+
+impl CostModel for PrimitiveOps {
+    fn name(&self) -> &'static str {
+        "every primitive op"
+    }
+
+    fn operation(&self, _operation: Operation) -> u64 {
+        1
+    }
+
+    fn transition(&self, operations: u64) -> u64 {
+        operations
+    }
+}
+
+/// Counts one per synchronous transition, however many heads it reads, writes and moves, as in
+/// the textbook model. An operation made on its own is a transition of its own.
+pub struct Transitions;
+
+impl CostModel for Transitions {
+    fn name(&self) -> &'static str {
+        "one per synchronous transition"
+    }
+
+    fn operation(&self, _operation: Operation) -> u64 {
+        1
+    }
+
+    fn transition(&self, _operations: u64) -> u64 {
+        1
+    }
+
+    fn halt(&self, _operations: u64) -> u64 {
+        0
+    }
+}
+
+thread_local! {
+    static COST_MODEL: RefCell<Rc<dyn CostModel>> = RefCell::new(Rc::new(PrimitiveOps));
+}
+
+/// Restores the previous cost model when dropped
+struct Restore(Option<Rc<dyn CostModel>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take().unwrap();
+        COST_MODEL.with(|cost_model| *cost_model.borrow_mut() = previous);
+    }
+}
+
+/// Runs `f` with tapes counting steps under `cost_model`
+pub fn with_cost_model<R>(cost_model: Rc<dyn CostModel>, f: impl FnOnce() -> R) -> R {
+    let _restore = Restore(Some(COST_MODEL.with(|current| current.replace(cost_model))));
+    f()
+}
+
+/// Name of the current cost model
+pub fn name() -> &'static str {
+    COST_MODEL.with(|cost_model| cost_model.borrow().name())
+}
+
+/// Cost of `operation` under the current cost model
+pub fn operation(operation: Operation) -> u64 {
+    COST_MODEL.with(|cost_model| cost_model.borrow().operation(operation))
+}
+
+/// Cost of a synchronous transition under the current cost model, see `CostModel::transition`
+pub fn transition(operations: u64) -> u64 {
+    COST_MODEL.with(|cost_model| cost_model.borrow().transition(operations))
+}
+
+/// Cost of halting under the current cost model, see `CostModel::halt`
+pub fn halt(operations: u64) -> u64 {
+    COST_MODEL.with(|cost_model| cost_model.borrow().halt(operations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{m1::M1, machine::TuringMachine};
+
+    #[test]
+    fn models() {
+        // M1 only makes operations on their own, so counting transitions counts every one. Checking
+        // its input takes 5 moves and 6 reads.
+        let cost_models: [(Rc<dyn CostModel>, _); 3] = [
+            (Rc::new(MovesOnly), 12),
+            (Rc::new(PrimitiveOps), 25),
+            (Rc::new(Transitions), 25),
+        ];
+        for (cost_model, steps) in cost_models {
+            let steps_ran = Rc::new(RefCell::new(0));
+            let (main_tape, aux_tape) = M1::input_for("ab", "b", Some(Rc::clone(&steps_ran)));
+            let name = cost_model.name();
+            assert!(with_cost_model(cost_model, || {
+                assert_eq!(super::name(), name);
+                M1::new(main_tape, aux_tape).run()
+            }));
+            assert_eq!(*steps_ran.borrow(), steps, "{}", name);
+        }
+        assert_eq!(name(), PrimitiveOps.name());
+    }
+}
@@ -0,0 +1,195 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{call_stack, m1::M1, machine::TuringMachine, tape::Move};
+
+/// State a head crossed a cell boundary in, as the innermost running machine and its state
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Crossing {
+    pub machine: Option<&'static str>,
+    pub state: Option<u32>,
+}
+
+impl Crossing {
+    fn current() -> Self {
+        let (machine, state) =
+            call_stack::current().map_or((None, None), |(machine, state)| (Some(machine), state));
+        Self { machine, state }
+    }
+}
+
+/// For example `M1:3`, or `_` outside of any machine
+impl fmt::Display for Crossing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.machine, self.state) {
+            (Some(machine), Some(state)) => write!(f, "{}:{}", machine, state),
+            (Some(machine), None) => write!(f, "{}", machine),
+            (None, _) => write!(f, "_"),
+        }
+    }
+}
+
+/// Head reversals of a tape, and the crossing sequence of each boundary between its cells if
+/// recording
+#[derive(Clone, Debug, Default)]
+pub struct Crossings {
+    /// Index of the cell the head started on, which moves right as the tape grows left
+    origin: usize,
+    /// Crossings of the left edge of each cell
+    sequences: Option<Vec<Vec<Crossing>>>,
+    last: Option<Move>,
+    reversals: u64,
+}
+
+impl Crossings {
+    pub fn new(origin: usize) -> Self {
+        Self {
+            origin,
+            ..Self::default()
+        }
+    }
+
+    /// Starts recording crossing sequences
+    pub fn record(&mut self) {
+        self.sequences.get_or_insert_with(Vec::new);
+    }
+
+    /// An empty cell has been added before the first one
+    pub fn grown_left(&mut self) {
+        self.origin += 1;
+        if let Some(sequences) = &mut self.sequences {
+            sequences.insert(0, vec![]);
+        }
+    }
+
+    /// The head has moved in `direction` across the left edge of the cell at `boundary`
+    pub fn cross(&mut self, boundary: usize, direction: Move) {
+        if self.last.is_some_and(|last| last != direction) {
+            self.reversals += 1;
+        }
+        self.last = Some(direction);
+        if let Some(sequences) = &mut self.sequences {
+            if sequences.len() <= boundary {
+                sequences.resize(boundary + 1, vec![]);
+            }
+            sequences[boundary].push(Crossing::current());
+        }
+    }
+
+    pub const fn reversals(&self) -> u64 {
+        self.reversals
+    }
+
+    /// Crossing sequence of each boundary crossed, by the position of the cell to its right
+    /// relative to the cell the head started on. Empty unless recording.
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn sequences(&self) -> BTreeMap<isize, Vec<Crossing>> {
+        self.sequences
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(|(_, sequence)| !sequence.is_empty())
+            .map(|(boundary, sequence)| {
+                (boundary as isize - self.origin as isize, sequence.clone())
+            })
+            .collect()
+    }
+}
+
+/// One line per boundary, its position then its crossing sequence, so that exports for different
+/// inputs can be compared line by line
+pub fn export(sequences: &BTreeMap<isize, Vec<Crossing>>) -> String {
+    sequences
+        .iter()
+        .map(|(boundary, sequence)| {
+            let sequence: Vec<_> = sequence.iter().map(ToString::to_string).collect();
+            format!("{}\t{}\n", boundary, sequence.join(" "))
+        })
+        .collect()
+}
+
+/// Prints the crossing sequences and head reversals of M1's main tape for each word of `mains`
+/// against `aux`, one export after another so they can be compared
+pub fn report(aux: &str, mains: &[String]) {
+    for main in mains {
+        let (main_tape, mut aux_tape) = M1::input_for(main, aux, None);
+        let mut main_tape = main_tape.with_crossings();
+        let output = M1::run_on(&mut main_tape, &mut aux_tape);
+        println!(
+            "# {}: {}, {} reversals",
+            main,
+            output,
+            main_tape.reversals()
+        );
+        print!("{}", export(&main_tape.crossing_sequences()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        machine::{AuxValue, MainValue},
+        tape::Tape,
+    };
+
+    /// M1 run on `main` and `aux`, with crossing sequences recorded on both tapes
+    fn m1(main: &str, aux: &str) -> (Tape<MainValue>, Tape<AuxValue>) {
+        let (main_tape, aux_tape) = M1::input_for(main, aux, None);
+        let (mut main_tape, mut aux_tape) = (main_tape.with_crossings(), aux_tape.with_crossings());
+        M1::run_on(&mut main_tape, &mut aux_tape);
+        (main_tape, aux_tape)
+    }
+
+    #[test]
+    fn reversals() {
+        let mut tape = Tape::<MainValue>::default();
+        tape.right();
+        tape.right();
+        tape.left();
+        tape.move_head(Move::Stay);
+        tape.left();
+        tape.left();
+        tape.right();
+        assert_eq!(tape.reversals(), 2);
+        // Not recording
+        assert!(tape.crossing_sequences().is_empty());
+    }
+
+    #[test]
+    fn sequences() {
+        let mut tape = Tape::<MainValue>::default().with_crossings();
+        tape.left();
+        tape.right();
+        tape.right();
+        let sequences = tape.crossing_sequences();
+        let none = Crossing {
+            machine: None,
+            state: None,
+        };
+        assert_eq!(sequences[&0], vec![none; 2]);
+        assert_eq!(sequences[&1], vec![none]);
+        assert_eq!(export(&sequences), "0\t_ _\n1\t_\n");
+    }
+
+    #[test]
+    fn m1_scans() {
+        // M1 scans the main tape left to right once, and the aux tape there and back
+        let (main_tape, aux_tape) = m1("ab", "b");
+        assert_eq!(main_tape.reversals(), 0);
+        assert_eq!(aux_tape.reversals(), 1);
+        assert_eq!(
+            export(&aux_tape.crossing_sequences()),
+            "1\tM1:0 M1:4\n2\tM1:0 M1:4\n"
+        );
+        let main = main_tape.crossing_sequences();
+        assert!(main.values().all(|sequence| sequence.len() == 1));
+        assert_eq!(export(&main), "1\tM1:6\n2\tM1:6\n3\tM1:11\n");
+
+        // The sequences agree across inputs up to where the aux word is matched
+        let (other_main_tape, _) = m1("aa", "b");
+        let other = other_main_tape.crossing_sequences();
+        assert_eq!(export(&other), "1\tM1:6\n2\tM1:6\n3\tM1:6\n");
+        assert_eq!(main[&2], other[&2]);
+        assert_ne!(main[&3], other[&3]);
+    }
+}
@@ -0,0 +1,287 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    alphabet::Alphabet,
+    call_stack,
+    m2::M2,
+    machine::{AuxValue, MainValue, Outcome},
+    multi_tape::StepCounting,
+    table::Table,
+    tape::{Tape, TapeConstructor, TapeValue},
+};
+
+/// Symbol on the queue tape of `Determinized`. A configuration is its state followed by the cells
+/// of its main tape and then of its aux tape, each marked if the head is on it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Queued<M: Alphabet, A: Alphabet> {
+    State(u32),
+    Main(TapeValue<M>, bool),
+    Aux(TapeValue<A>, bool),
+    /// Cell of a configuration that has been dequeued
+    Done,
+}
+
+/// Deterministic machine accepting the same inputs as a nondeterministic table machine, by
+/// searching its configurations breadth first with a queue of them on an extra tape. Steps are
+/// counted for the simulated transitions and for every move, read and write on the queue tape.
+pub struct Determinized<M: Alphabet, A: Alphabet> {
+    table: Table<M, A>,
+}
+
+impl<M: Alphabet, A: Alphabet> Determinized<M, A> {
+    pub const fn new(table: Table<M, A>) -> Self {
+        Self { table }
+    }
+
+    /// Runs the search on borrowed tapes, leaving them as the first accepting configuration found
+    /// left them. Rejects once the queue is empty, so may not halt if the table machine has
+    /// infinite branches and no accepting one.
+    pub fn run_on(
+        &self,
+        main_tape: &mut Tape<M>,
+        aux_tape: &mut Tape<A>,
+        queue_tape: &mut Tape<Queued<M, A>>,
+    ) -> bool {
+        let _invocation = call_stack::enter("Determinized", queue_tape.steps_ran_counter());
+        enqueue(queue_tape, self.table.start(), main_tape, aux_tape);
+        loop {
+            let (state, main, aux) = if let Some(configuration) = dequeue(queue_tape) {
+                configuration
+            } else {
+                return false;
+            };
+            let main = with_bounds_of(main, main_tape);
+            let aux = with_bounds_of(aux, aux_tape);
+            call_stack::state(state);
+            if state == self.table.accept() {
+                (*main_tape, *aux_tape) = (main, aux);
+                return true;
+            }
+
+            let (main_value, aux_value) = (main.read(), aux.read());
+            let rules = self.table.rules_for(state, &main_value, &aux_value);
+            for rule in rules {
+                // Branches that crash or violate a bound are dropped like those that reject
+                let (mut main, mut aux) = (main.clone(), aux.clone());
+                rule.apply(&mut main, &mut aux);
+                if Outcome::of_tape(&main).is_none() && Outcome::of_tape(&aux).is_none() {
+                    enqueue(queue_tape, rule.next, &main, &aux);
+                }
+            }
+        }
+    }
+}
+
+/// `tape` with the left end and end markers of `bounds`
+const fn with_bounds_of<T: Clone, U: Clone>(tape: Tape<T>, bounds: &Tape<U>) -> Tape<T> {
+    let tape = tape.with_left_end(bounds.left_end());
+    if bounds.has_end_markers() {
+        tape.with_end_markers()
+    } else {
+        tape
+    }
+}
+
+/// Writes a configuration after the last one on the queue, then returns to the first
+fn enqueue<M: Alphabet, A: Alphabet>(
+    queue_tape: &mut Tape<Queued<M, A>>,
+    state: u32,
+    main_tape: &Tape<M>,
+    aux_tape: &Tape<A>,
+) {
+    while queue_tape.read() != TapeValue::Empty {
+        queue_tape.right();
+    }
+    queue_tape.write(TapeValue::Value(Queued::State(state)));
+    for (index, value) in main_tape.data().iter().enumerate() {
+        queue_tape.right();
+        queue_tape.write(TapeValue::Value(Queued::Main(
+            value.clone(),
+            index == main_tape.head(),
+        )));
+    }
+    for (index, value) in aux_tape.data().iter().enumerate() {
+        queue_tape.right();
+        queue_tape.write(TapeValue::Value(Queued::Aux(
+            value.clone(),
+            index == aux_tape.head(),
+        )));
+    }
+
+    loop {
+        queue_tape.left();
+        match queue_tape.read() {
+            TapeValue::Value(Queued::Done) | TapeValue::Empty => break,
+            TapeValue::Value(_) => {}
+        }
+    }
+    queue_tape.right();
+}
+
+/// Marks the first configuration on the queue as done and returns it, `None` if the queue is
+/// empty
+fn dequeue<M: Alphabet, A: Alphabet>(
+    queue_tape: &mut Tape<Queued<M, A>>,
+) -> Option<(u32, Tape<M>, Tape<A>)> {
+    let state = match queue_tape.read() {
+        TapeValue::Value(Queued::State(state)) => state,
+        TapeValue::Empty => return None,
+        TapeValue::Value(_) => unreachable!("Queue must start with a state"),
+    };
+    queue_tape.write(TapeValue::Value(Queued::Done));
+    queue_tape.right();
+
+    let (mut main_data, mut aux_data) = (vec![], vec![]);
+    loop {
+        match queue_tape.read() {
+            TapeValue::Value(Queued::Main(value, head)) => main_data.push(if head {
+                TapeConstructor::Head(value)
+            } else {
+                TapeConstructor::Value(value)
+            }),
+            TapeValue::Value(Queued::Aux(value, head)) => aux_data.push(if head {
+                TapeConstructor::Head(value)
+            } else {
+                TapeConstructor::Value(value)
+            }),
+            TapeValue::Value(Queued::State(_)) | TapeValue::Empty => break,
+            TapeValue::Value(Queued::Done) => unreachable!("Queue must not have gaps"),
+        }
+        queue_tape.write(TapeValue::Value(Queued::Done));
+        queue_tape.right();
+    }
+    Some((
+        state,
+        Tape::new(main_data, queue_tape.steps_ran_counter()),
+        Tape::new(aux_data, queue_tape.steps_ran_counter()),
+    ))
+}
+
+/// Whether the determinized table accepts `word`, and the steps it took
+fn determinized(table: &Table<MainValue, AuxValue>, word: &str) -> (bool, u64) {
+    let steps_ran = Rc::new(RefCell::new(0));
+    let (mut main_tape, mut aux_tape) = M2::input_for(word, Some(Rc::clone(&steps_ran)));
+    let mut queue_tape = Tape::new(
+        vec![TapeConstructor::Head(TapeValue::Empty)],
+        Some(Rc::clone(&steps_ran)),
+    );
+    let output =
+        Determinized::new(table.clone()).run_on(&mut main_tape, &mut aux_tape, &mut queue_tape);
+    let steps_ran = *steps_ran.borrow();
+    (output, steps_ran)
+}
+
+/// Steps taken by one branch of the `M2` table and by its determinized search on `word`
+fn m2_steps(word: &str) -> (u64, u64) {
+    // Both count each read, write and move, as the search takes more of each per transition
+    let table = M2::table().with_step_counting(StepCounting::PerOperation);
+
+    let steps_ran = Rc::new(RefCell::new(0));
+    let (mut main_tape, mut aux_tape) = M2::input_for(word, Some(Rc::clone(&steps_ran)));
+    assert!(table.run_on(&mut main_tape, &mut aux_tape));
+    let (output, determinized_steps) = determinized(&table, word);
+    assert!(output);
+    let steps = (*steps_ran.borrow(), determinized_steps);
+    steps
+}
+
+/// Compares the steps taken by one branch of the `M2` table with its determinized search as the
+/// word grows a letter at a time
+pub fn report(max_length: usize) {
+    println!("length\tone branch\tdeterminized\tratio");
+    for length in 1..=max_length {
+        let (one_branch, determinized) = m2_steps(&"a".repeat(length));
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = determinized as f64 / one_branch as f64;
+        println!("{}\t{}\t{}\t{:.1}", length, one_branch, determinized, ratio);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chooser::explore,
+        table::Rule,
+        tape::Move::{Right, Stay},
+        test_support::words,
+    };
+
+    /// Accepts if the word contains bb, by guessing where it starts
+    fn contains_bb() -> Table<MainValue, AuxValue> {
+        let a = Some(TapeValue::Value(MainValue::A));
+        let b = Some(TapeValue::Value(MainValue::B));
+        Table::new(
+            "Contains bb",
+            0,
+            3,
+            vec![
+                Rule::new(0, (None, None), 1, (None, None), (Right, Stay)),
+                Rule::new(1, (a, None), 1, (None, None), (Right, Stay)),
+                Rule::new(1, (b.clone(), None), 1, (None, None), (Right, Stay)),
+                Rule::new(1, (b.clone(), None), 2, (None, None), (Right, Stay)),
+                Rule::new(2, (b, None), 3, (None, None), (Stay, Stay)),
+            ],
+        )
+    }
+
+    #[test]
+    fn same_as_exploration() {
+        for table in [contains_bb(), M2::table()] {
+            for word in words("ab", 1..6) {
+                let (main_tape, aux_tape) = M2::input_for(&word, None);
+                let exploration = explore(true, || {
+                    table.run_on(&mut main_tape.clone(), &mut aux_tape.clone())
+                });
+
+                assert_eq!(
+                    determinized(&table, &word).0,
+                    exploration.accepted.is_some(),
+                    "{} {}",
+                    table.name(),
+                    word
+                );
+            }
+        }
+        assert!(determinized(&contains_bb(), "abba").0);
+        assert!(!determinized(&contains_bb(), "abab").0);
+    }
+
+    #[test]
+    fn first_accepting() {
+        let (mut main_tape, mut aux_tape) = M2::input_for("bab", None);
+        let mut queue_tape = Tape::default();
+        assert!(Determinized::new(M2::table()).run_on(
+            &mut main_tape,
+            &mut aux_tape,
+            &mut queue_tape
+        ));
+        // Every branch accepts at the same depth, so the first found writes a at every choice
+        assert_eq!(main_tape.read(), TapeValue::Value(MainValue::Hash));
+        assert_eq!(aux_tape.head(), 0);
+        assert_eq!(
+            &aux_tape.data()[1..],
+            vec![TapeValue::Value(AuxValue::A); 3]
+        );
+    }
+
+    #[test]
+    fn blow_up() {
+        // Each letter doubles the configurations M2 can reach, so the search takes at least twice
+        // as many steps, while a single branch only takes a few more
+        let table = M2::table();
+        let mut last_steps = determinized(&table, "a").1;
+        for length in 2..8 {
+            let word = "a".repeat(length);
+            let steps = determinized(&table, &word).1;
+            assert!(steps > 2 * last_steps, "{} {}", steps, last_steps);
+            last_steps = steps;
+        }
+
+        let steps_ran = Rc::new(RefCell::new(0));
+        let (mut main_tape, mut aux_tape) = M2::input_for("aaaaaaa", Some(Rc::clone(&steps_ran)));
+        assert!(table.run_on(&mut main_tape, &mut aux_tape));
+        assert!(*steps_ran.borrow() < 100);
+        assert!(last_steps > 10_000);
+    }
+}
@@ -0,0 +1,290 @@
+use rand::{seq::SliceRandom, Rng};
+use std::{
+    cell::RefCell,
+    fmt::{self, Display},
+    ops::RangeInclusive,
+    rc::Rc,
+};
+
+use crate::{
+    alphabet::Alphabet,
+    machine::TuringMachine,
+    pattern::{Class, InputShape, Node, Pattern},
+    tape::{Tape, TapeConstructor, TapeValue},
+};
+
+/// Random tapes of the shape a machine declares for its input, see `InputShape`. Each repetition
+/// in a shape is drawn from one of the ranges: runs of empty cells from `padding`, runs of any
+/// other single cell, such as the letters of a word, from `length`, and repeated groups, such as
+/// `(# (a|b)+)*`, one fewer times than a number of words from `words`, for the word before them.
+/// Counts are then kept within what the shape allows.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Generator {
+    words: RangeInclusive<usize>,
+    length: RangeInclusive<usize>,
+    padding: RangeInclusive<usize>,
+    bias: Option<bool>,
+    attempts: usize,
+}
+
+impl Generator {
+    /// No padding and no bias
+    pub const fn new(words: RangeInclusive<usize>, length: RangeInclusive<usize>) -> Self {
+        Self {
+            words,
+            length,
+            padding: 0..=0,
+            bias: None,
+            attempts: 0,
+        }
+    }
+
+    #[must_use]
+    #[cfg(test)]
+    pub const fn with_padding(mut self, padding: RangeInclusive<usize>) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Draws up to `attempts` inputs until one the machine accepts, or rejects if not `accept`,
+    /// as decided by its `Oracle`, and otherwise fails with the last, see `BiasMissed`
+    #[must_use]
+    pub fn with_bias(mut self, accept: bool, attempts: usize) -> Self {
+        assert!(attempts > 0, "Generator must make at least one attempt");
+        self.bias = Some(accept);
+        self.attempts = attempts;
+        self
+    }
+
+    /// Main and aux tapes for `M`, counting steps on `steps_ran`. Fails if biased and no attempt
+    /// went the way of the bias.
+    pub fn tapes<M: InputShape + Oracle>(
+        &self,
+        rng: &mut impl Rng,
+        steps_ran: Option<Rc<RefCell<u64>>>,
+    ) -> Drawn<M> {
+        let main_shape = Pattern::cached(M::MAIN_SHAPE);
+        let aux_shape = Pattern::cached(M::AUX_SHAPE);
+        let mut draw = || (self.cells(&main_shape, rng), self.cells(&aux_shape, rng));
+
+        let (mut main_data, mut aux_data) = draw();
+        let mut missed = None;
+        if let Some(accept) = self.bias {
+            for attempt in 1..=self.attempts {
+                let main_tape = Tape::new(main_data.clone(), None);
+                let aux_tape = Tape::new(aux_data.clone(), None);
+                if M::accepts(&main_tape, &aux_tape) == accept {
+                    break;
+                }
+                if attempt == self.attempts {
+                    missed = Some((accept, attempt));
+                } else {
+                    (main_data, aux_data) = draw();
+                }
+            }
+        }
+        let tapes = (
+            Tape::new(main_data, steps_ran.clone()),
+            Tape::new(aux_data, steps_ran),
+        );
+        match missed {
+            Some((accept, attempts)) => Err(BiasMissed {
+                accept,
+                attempts,
+                tapes: Box::new(tapes),
+            }),
+            None => Ok(tapes),
+        }
+    }
+
+    /// Cells of a tape matching `pattern`
+    pub fn cells<T: Alphabet>(
+        &self,
+        pattern: &Pattern<T>,
+        rng: &mut impl Rng,
+    ) -> Vec<TapeConstructor<T>> {
+        let mut cells = vec![];
+        self.generate(pattern.node(), rng, &mut cells);
+        cells
+    }
+
+    fn generate<T: Alphabet>(
+        &self,
+        node: &Node<T>,
+        rng: &mut impl Rng,
+        cells: &mut Vec<TapeConstructor<T>>,
+    ) {
+        match node {
+            Node::Cell { class, head } => {
+                let cell = match class {
+                    Class::Any => {
+                        let index = rng.gen_range(0..=T::symbols().len());
+                        T::symbols()
+                            .get(index)
+                            .cloned()
+                            .map_or(TapeValue::Empty, TapeValue::Value)
+                    }
+                    Class::OneOf(cells) => cells.choose(rng).unwrap().clone(),
+                };
+                cells.push(if *head {
+                    TapeConstructor::Head(cell)
+                } else {
+                    TapeConstructor::Value(cell)
+                });
+            }
+            Node::Sequence(nodes) => {
+                for node in nodes {
+                    self.generate(node, rng, cells);
+                }
+            }
+            Node::Alternatives(nodes) => self.generate(nodes.choose(rng).unwrap(), rng, cells),
+            Node::Repeat { node, min, max } => {
+                let count = match &**node {
+                    Node::Cell {
+                        class: Class::OneOf(class),
+                        head: false,
+                    } if class.iter().all(|cell| cell == &TapeValue::Empty) => {
+                        rng.gen_range(self.padding.clone())
+                    }
+                    Node::Cell { .. } => rng.gen_range(self.length.clone()),
+                    _ => rng.gen_range(self.words.clone()).saturating_sub(1),
+                };
+                for _ in 0..count.clamp(*min, max.unwrap_or(usize::MAX)) {
+                    self.generate(node, rng, cells);
+                }
+            }
+        }
+    }
+}
+
+/// Main and aux tapes for `M` drawn by `Generator::tapes`
+type Drawn<M> = Result<
+    (
+        Tape<<M as TuringMachine>::Main>,
+        Tape<<M as TuringMachine>::Aux>,
+    ),
+    BiasMissed<<M as TuringMachine>::Main, <M as TuringMachine>::Aux>,
+>;
+
+/// No input drawn by a biased generator went the way of its bias, see `Generator::with_bias`
+#[derive(Debug)]
+pub struct BiasMissed<M: Alphabet, A: Alphabet> {
+    accept: bool,
+    attempts: usize,
+    tapes: Box<(Tape<M>, Tape<A>)>,
+}
+
+impl<M: Alphabet, A: Alphabet> BiasMissed<M, A> {
+    /// The last input drawn, which the machine rejects if biased towards accepting and the other
+    /// way round
+    pub fn into_tapes(self) -> (Tape<M>, Tape<A>) {
+        *self.tapes
+    }
+}
+
+impl<M: Alphabet, A: Alphabet> Display for BiasMissed<M, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "No input the machine {} in {} attempts",
+            if self.accept { "accepts" } else { "rejects" },
+            self.attempts
+        )
+    }
+}
+
+/// Machine with a reference decision of whether it accepts an input, made from the words on its
+/// tapes rather than by running it, so that it takes no steps and no time exponential in its
+/// choices
+pub trait Oracle: TuringMachine {
+    /// Whether some branch of the machine's choices accepts tapes of its input shape without end
+    /// markers
+    fn accepts(main_tape: &Tape<Self::Main>, aux_tape: &Tape<Self::Aux>) -> bool;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chooser::explore, m1::M1, m2::M2, m3::M3};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    /// Whether `M` accepts the tapes, running it once for every branch of its choices until one
+    /// accepts
+    fn explored<M: TuringMachine>(main_tape: &Tape<M::Main>, aux_tape: &Tape<M::Aux>) -> bool {
+        explore(true, || {
+            M::run_on(&mut main_tape.clone(), &mut aux_tape.clone())
+        })
+        .accepted
+        .is_some()
+    }
+
+    #[test]
+    fn oracles() {
+        // M3 runs M2 on its first word, so needs a word after it
+        let mut rng = StdRng::seed_from_u64(0);
+        let generator = Generator::new(2..=4, 1..=4).with_padding(0..=1);
+        for _ in 0..100 {
+            let (main_tape, aux_tape) = generator.tapes::<M1>(&mut rng, None).unwrap();
+            assert_eq!(
+                M1::accepts(&main_tape, &aux_tape),
+                explored::<M1>(&main_tape, &aux_tape)
+            );
+            let (main_tape, aux_tape) = generator.tapes::<M2>(&mut rng, None).unwrap();
+            assert!(M2::accepts(&main_tape, &aux_tape));
+            assert!(explored::<M2>(&main_tape, &aux_tape));
+            let (main_tape, aux_tape) = generator.tapes::<M3>(&mut rng, None).unwrap();
+            assert_eq!(
+                M3::accepts(&main_tape, &aux_tape),
+                explored::<M3>(&main_tape, &aux_tape),
+                "{}",
+                main_tape
+                    .words_from(main_tape.head() + 1)
+                    .map(|word| word.to_string())
+                    .collect::<Vec<_>>()
+                    .join("#")
+            );
+        }
+    }
+
+    #[test]
+    fn shapes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let generator = Generator::new(1..=4, 1..=5).with_padding(0..=2);
+        for _ in 0..50 {
+            // Each machine checks its input against its shape
+            let (main_tape, aux_tape) = generator.tapes::<M1>(&mut rng, None).unwrap();
+            M1::new(main_tape, aux_tape);
+            let (main_tape, aux_tape) = generator.tapes::<M2>(&mut rng, None).unwrap();
+            M2::new(main_tape, aux_tape);
+            let (main_tape, aux_tape) = generator.tapes::<M3>(&mut rng, None).unwrap();
+            let words: Vec<_> = main_tape.words_from(main_tape.head() + 1).collect();
+            assert!((1..=4).contains(&words.len()));
+            assert!(words.iter().all(|word| (1..=5).contains(&word.len())));
+            M3::new(main_tape, aux_tape);
+        }
+    }
+
+    #[test]
+    fn bias() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for accept in [true, false] {
+            let generator = Generator::new(3..=3, 2..=3).with_bias(accept, 100);
+            for _ in 0..10 {
+                let (main_tape, aux_tape) = generator.tapes::<M3>(&mut rng, None).unwrap();
+                assert_eq!(M3::accepts(&main_tape, &aux_tape), accept);
+            }
+        }
+
+        // M2 accepts every input of its shape
+        let missed = Generator::new(1..=1, 1..=2)
+            .with_bias(false, 3)
+            .tapes::<M2>(&mut rng, None)
+            .unwrap_err();
+        assert_eq!(
+            missed.to_string(),
+            "No input the machine rejects in 3 attempts"
+        );
+        let (main_tape, aux_tape) = missed.into_tapes();
+        assert!(M2::accepts(&main_tape, &aux_tape));
+    }
+}
@@ -0,0 +1,106 @@
+use rand::rngs::StdRng;
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    generate::{Generator, Oracle},
+    m3::M3,
+    machine::{AuxValue, MainValue, TuringMachine},
+    space::Space,
+    tape::Tape,
+};
+
+/// Inputs to measure M3 on
+pub enum Inputs {
+    /// Copies of a word of alternating a's and b's
+    Alternating,
+    /// Random words drawn from `rng`, biased towards inputs M3 accepts, or rejects if not
+    /// `accept`, when `bias` is `Some(accept)`. An input the bias missed is measured all the same,
+    /// and the `accepts` column shows which way it went.
+    Random {
+        rng: Box<StdRng>,
+        bias: Option<bool>,
+    },
+}
+
+impl Inputs {
+    /// Tapes of `words` words of `length` letters, counting steps on `steps_ran`, and whether M3
+    /// accepts them if drawing them already decided it
+    fn tapes(
+        &mut self,
+        words: usize,
+        length: usize,
+        steps_ran: &Rc<RefCell<u64>>,
+    ) -> (Tape<MainValue>, Tape<AuxValue>, Option<bool>) {
+        match self {
+            Self::Alternating => {
+                let word: String = "ab".chars().cycle().take(length).collect();
+                let (main_tape, aux_tape) =
+                    M3::input_for(&vec![word.as_str(); words], Some(Rc::clone(steps_ran)));
+                (main_tape, aux_tape, None)
+            }
+            Self::Random { rng, bias } => {
+                let mut generator = Generator::new(words..=words, length..=length);
+                if let Some(accept) = bias {
+                    generator = generator.with_bias(*accept, 100);
+                }
+                match generator.tapes::<M3>(&mut **rng, Some(Rc::clone(steps_ran))) {
+                    Ok((main_tape, aux_tape)) => (main_tape, aux_tape, *bias),
+                    Err(missed) => {
+                        let (main_tape, aux_tape) = missed.into_tapes();
+                        (main_tape, aux_tape, bias.map(|accept| !accept))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Whether M3 accepts `words` words of `length` letters from `inputs`, then the result, steps and
+/// space of one run making choices with the current chooser
+fn measure(inputs: &mut Inputs, words: usize, length: usize) -> (bool, bool, u64, Space, Space) {
+    let steps_ran = Rc::new(RefCell::new(0));
+    let (main_tape, aux_tape, accepts) = inputs.tapes(words, length, &steps_ran);
+    let accepts = accepts.unwrap_or_else(|| M3::accepts(&main_tape, &aux_tape));
+
+    let mut m3 = M3::new(main_tape, aux_tape);
+    let output = m3.run();
+    let destroy = m3.destroy();
+    let steps_ran = *steps_ran.borrow();
+    (
+        accepts,
+        output,
+        steps_ran,
+        destroy.main_tape().space(),
+        destroy.aux_tape().space(),
+    )
+}
+
+/// Prints whether M3 accepts, then the result, steps and space of one run making choices with the
+/// current chooser, over numbers of words doubling from 2, as M2 needs a word after the first, and
+/// lengths of words doubling from 1
+pub fn grid(max_words: usize, max_length: usize, inputs: &mut Inputs) {
+    println!(
+        "words\tlength\taccepts\tresult\tsteps\tmain visited\tmain written\taux visited\taux written"
+    );
+    let mut words = 2;
+    while words <= max_words {
+        let mut length = 1;
+        while length <= max_length {
+            let (accepts, output, steps, main, aux) = measure(inputs, words, length);
+            println!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                words,
+                length,
+                accepts,
+                output,
+                steps,
+                main.visited,
+                main.written,
+                aux.visited,
+                aux.written
+            );
+            length *= 2;
+        }
+        words *= 2;
+    }
+}
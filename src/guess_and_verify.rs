@@ -0,0 +1,261 @@
+use log::trace;
+use std::{marker::PhantomData, ops::RangeInclusive};
+
+use crate::{
+    alphabet::Alphabet,
+    call_stack,
+    machine::{certificate_tape, choose_among, DestroyOutput, TuringMachine, Verifier},
+    tape::{Tape, TapeValue},
+};
+
+/// How long the guessed certificate is
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum GuessLength {
+    #[allow(dead_code)]
+    Exactly(usize),
+    /// Any length from 1 up to and including this, which must be at least 1
+    #[allow(dead_code)]
+    UpTo(usize),
+    /// Length of the word of certificate symbols on the right of the main tape head, as guessed by
+    /// `M2`
+    FirstWord,
+}
+
+impl GuessLength {
+    fn lengths<M: Alphabet, C: Alphabet>(&self, main_tape: &Tape<M>) -> RangeInclusive<usize> {
+        match self {
+            Self::Exactly(length) => *length..=*length,
+            Self::UpTo(length) => 1..=*length,
+            Self::FirstWord => {
+                let length = main_tape.data()[main_tape.head() + 1..]
+                    .iter()
+                    .take_while(|value| match value {
+                        TapeValue::Value(symbol) => C::from_char(symbol.to_char()).is_some(),
+                        TapeValue::Empty => false,
+                    })
+                    .count();
+                length..=length
+            }
+        }
+    }
+}
+
+/// How the certificate is guessed
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Mode<T: Alphabet> {
+    /// Guess a single certificate with `choose_among`
+    Random,
+    /// Use the given certificate
+    #[allow(dead_code)]
+    Replay(Vec<T>),
+    /// Try every certificate in order until one is accepted
+    Exhaustive,
+}
+
+/// Nondeterministic machine that guesses a certificate and runs a deterministic verifier on it
+pub struct GuessAndVerify<V: Verifier> {
+    main_tape: Tape<V::Main>,
+    aux_tape: Tape<V::Aux>,
+    guess_length: GuessLength,
+    mode: Mode<V::Aux>,
+    certificate: Option<Vec<V::Aux>>,
+    verifier: PhantomData<V>,
+}
+
+impl<V: Verifier> TuringMachine for GuessAndVerify<V> {
+    type Main = V::Main;
+    type Aux = V::Aux;
+
+    fn new(main_tape: Tape<V::Main>, aux_tape: Tape<V::Aux>) -> Self {
+        Self::with_mode(main_tape, aux_tape, GuessLength::FirstWord, Mode::Random)
+    }
+
+    fn run(&mut self) -> bool {
+        let _invocation = call_stack::enter("GuessAndVerify", self.main_tape.steps_ran_counter());
+        let lengths = self.guess_length.lengths::<_, V::Aux>(&self.main_tape);
+        let symbols = V::Aux::symbols();
+        match self.mode.clone() {
+            Mode::Random => {
+                let length = lengths.start() + choose_among(lengths.end() + 1 - lengths.start());
+                let certificate = (0..length)
+                    .map(|_| symbols[choose_among(symbols.len())].clone())
+                    .collect();
+                self.verify(certificate)
+            }
+            Mode::Replay(certificate) => self.verify(certificate),
+            Mode::Exhaustive => {
+                for length in lengths {
+                    let mut indices = vec![0; length];
+                    loop {
+                        let certificate = indices
+                            .iter()
+                            .map(|index| symbols[*index].clone())
+                            .collect();
+                        if self.verify(certificate) {
+                            return true;
+                        }
+                        // Next certificate in lexicographic order
+                        match indices.iter().rposition(|index| index + 1 < symbols.len()) {
+                            Some(position) => {
+                                indices[position] += 1;
+                                for index in &mut indices[position + 1..] {
+                                    *index = 0;
+                                }
+                            }
+                            None => break,
+                        }
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    fn destroy(self) -> DestroyOutput<V::Main, V::Aux> {
+        DestroyOutput::new(self.main_tape, self.aux_tape)
+    }
+}
+
+impl<V: Verifier> GuessAndVerify<V> {
+    pub const fn with_mode(
+        main_tape: Tape<V::Main>,
+        aux_tape: Tape<V::Aux>,
+        guess_length: GuessLength,
+        mode: Mode<V::Aux>,
+    ) -> Self {
+        assert!(
+            !matches!(guess_length, GuessLength::UpTo(0)),
+            "GuessLength::UpTo must allow a length of at least 1"
+        );
+        Self {
+            main_tape,
+            aux_tape,
+            guess_length,
+            mode,
+            certificate: None,
+            verifier: PhantomData,
+        }
+    }
+
+    /// Accepted certificate, `None` if the machine has not accepted
+    pub fn certificate(&self) -> Option<&[V::Aux]> {
+        self.certificate.as_deref()
+    }
+
+    /// Runs the verifier on the certificate, keeping its tapes if it accepts
+    fn verify(&mut self, certificate: Vec<V::Aux>) -> bool {
+        trace!("GuessAndVerify: {:?}", certificate);
+        let steps_ran = self.main_tape.steps_ran_counter();
+        let mut verifier = V::with_certificate(
+            self.main_tape.clone(),
+            self.aux_tape.clone(),
+            certificate_tape(&certificate, steps_ran),
+        );
+        if !verifier.run() {
+            return false;
+        }
+
+        let destroy = verifier.destroy();
+        self.main_tape = destroy.main_tape().clone();
+        self.aux_tape = destroy.aux_tape().clone();
+        self.certificate = Some(certificate);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{m2::M2, m3::M3, machine::AuxValue};
+
+    #[test]
+    fn exhaustive() {
+        let (main_tape, aux_tape) = M3::input_for(&["aba", "abba", "aabba"], None);
+        let mut machine = GuessAndVerify::<M3>::with_mode(
+            main_tape,
+            aux_tape,
+            GuessLength::FirstWord,
+            Mode::Exhaustive,
+        );
+        assert!(machine.run());
+        let certificate = machine.certificate().unwrap().to_vec();
+        assert_eq!(certificate, vec![AuxValue::A, AuxValue::B, AuxValue::A]);
+        let (main_tape, aux_tape) = M3::input_for(&["aba", "abba", "aabba"], None);
+        assert!(M3::accepts_certificate(
+            main_tape,
+            aux_tape,
+            certificate_tape(&certificate, None)
+        ));
+
+        let (main_tape, aux_tape) = M3::input_for(&["ab", "aa", "bb"], None);
+        let mut machine = GuessAndVerify::<M3>::with_mode(
+            main_tape,
+            aux_tape,
+            GuessLength::FirstWord,
+            Mode::Exhaustive,
+        );
+        assert!(!machine.run());
+        assert_eq!(machine.certificate(), None);
+    }
+
+    #[test]
+    fn guess_length() {
+        for (guess_length, accepted) in [
+            (GuessLength::Exactly(2), false),
+            (GuessLength::Exactly(3), true),
+            (GuessLength::UpTo(2), false),
+            (GuessLength::UpTo(4), true),
+        ] {
+            let (main_tape, aux_tape) = M2::input_for("abb", None);
+            let mut machine = GuessAndVerify::<M2>::with_mode(
+                main_tape,
+                aux_tape,
+                guess_length.clone(),
+                Mode::Exhaustive,
+            );
+            assert_eq!(machine.run(), accepted, "{:?}", guess_length);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "GuessLength::UpTo must allow a length of at least 1")]
+    fn up_to_zero() {
+        let (main_tape, aux_tape) = M2::input_for("abb", None);
+        GuessAndVerify::<M2>::with_mode(main_tape, aux_tape, GuessLength::UpTo(0), Mode::Random);
+    }
+
+    #[test]
+    fn replay() {
+        let (main_tape, aux_tape) = M3::input_for(&["ab", "ba", "aab"], None);
+        let mut machine = GuessAndVerify::<M3>::with_mode(
+            main_tape,
+            aux_tape,
+            GuessLength::FirstWord,
+            Mode::Replay(vec![AuxValue::B, AuxValue::A]),
+        );
+        assert!(!machine.run());
+
+        let (main_tape, aux_tape) = M3::input_for(&["ab", "bab", "aab"], None);
+        let mut machine = GuessAndVerify::<M3>::with_mode(
+            main_tape,
+            aux_tape,
+            GuessLength::FirstWord,
+            Mode::Replay(vec![AuxValue::A, AuxValue::B]),
+        );
+        assert!(machine.run());
+        assert_eq!(machine.certificate(), Some(&[AuxValue::A, AuxValue::B][..]));
+    }
+
+    #[test]
+    fn random() {
+        for _ in 0..20 {
+            let (main_tape, aux_tape) = M3::input_for(&["ab", "bab", "aab"], None);
+            let mut machine = GuessAndVerify::<M3>::new(main_tape, aux_tape);
+            if machine.run() {
+                assert_eq!(machine.certificate(), Some(&[AuxValue::A, AuxValue::B][..]));
+            } else {
+                assert_eq!(machine.certificate(), None);
+            }
+        }
+    }
+}
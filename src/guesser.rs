@@ -0,0 +1,245 @@
+use log::trace;
+
+use crate::{
+    alphabet::{Alphabet, WordAlphabet},
+    call_stack,
+    generate::Oracle,
+    machine::{choose_among, DestroyOutput, TuringMachine, Verifier},
+    pattern::{InputShape, Walk},
+    tape::{Tape, TapeValue},
+};
+
+/// Guesses a word as long as the word on the main tape, over any word alphabet. `M2` is this over
+/// `MainValue`. Rejects as soon as a move crashes or violates a bound, see `Tape::has_halted`.
+pub struct Guesser<M: WordAlphabet> {
+    main_tape: Tape<M>,
+    aux_tape: Tape<M::Letter>,
+    certificate_tape: Option<Tape<M::Letter>>,
+}
+
+impl<M: WordAlphabet> TuringMachine for Guesser<M> {
+    type Main = M;
+    type Aux = M::Letter;
+
+    fn new(main_tape: Tape<M>, aux_tape: Tape<M::Letter>) -> Self {
+        Self::check_input(&main_tape, &aux_tape);
+
+        Self {
+            main_tape,
+            aux_tape,
+            certificate_tape: None,
+        }
+    }
+
+    fn run(&mut self) -> bool {
+        let _invocation = call_stack::enter(Self::NAME, self.main_tape.steps_ran_counter());
+        self.zero()
+    }
+
+    fn destroy(self) -> DestroyOutput<M, M::Letter> {
+        DestroyOutput::new(self.main_tape, self.aux_tape)
+    }
+}
+
+/// Word after an empty head, ended by a separator, and a blank aux tape
+impl<M: WordAlphabet> InputShape for Guesser<M> {
+    const NAME: &'static str = M::MACHINE_NAMES.guesser;
+    const MAIN_SHAPE: &'static str = r"_* [_] \w+ \W .*";
+    const AUX_SHAPE: &'static str = "_* [_] _*";
+    const MAIN_WALK: Walk = Walk::Word { read_head: false };
+    const AUX_WALK: Walk = Walk::Still;
+}
+
+/// Accepts every input of its shape, whatever it guesses
+impl<M: WordAlphabet> Oracle for Guesser<M> {
+    fn accepts(_main_tape: &Tape<M>, _aux_tape: &Tape<M::Letter>) -> bool {
+        true
+    }
+}
+
+/// Deterministic variant that reads its choices from the certificate tape instead of choosing,
+/// rejecting if the certificate is not exactly as long as the word
+impl<M: WordAlphabet> Verifier for Guesser<M> {
+    fn with_certificate(
+        main_tape: Tape<M>,
+        aux_tape: Tape<M::Letter>,
+        certificate_tape: Tape<M::Letter>,
+    ) -> Self {
+        // Certificate tape head must be empty, the certificate starts on its right
+        assert_eq!(
+            certificate_tape.data()[certificate_tape.head()],
+            TapeValue::Empty,
+            "Guesser certificate tape head must be empty"
+        );
+
+        let mut guesser = Self::new(main_tape, aux_tape);
+        guesser.certificate_tape = Some(certificate_tape);
+        guesser
+    }
+}
+
+impl<M: WordAlphabet> Guesser<M> {
+    /// Next letter, `None` if the certificate has run out
+    fn pick(&mut self) -> Option<M::Letter> {
+        match self.certificate_tape.as_mut() {
+            None => {
+                let letters = M::Letter::symbols();
+                Some(letters[choose_among(letters.len())].clone())
+            }
+            Some(certificate_tape) => {
+                certificate_tape.right();
+                match certificate_tape.read() {
+                    TapeValue::Value(letter) => Some(letter),
+                    TapeValue::Empty => None,
+                }
+            }
+        }
+    }
+
+    fn is_certificate_exhausted(&mut self) -> bool {
+        match self.certificate_tape.as_mut() {
+            None => true,
+            Some(certificate_tape) => {
+                certificate_tape.right();
+                certificate_tape.read() == TapeValue::Empty
+            }
+        }
+    }
+}
+
+// Run
+impl<M: WordAlphabet> Guesser<M> {
+    /// States 0 to 8 are those of `M2`, which writes the first letter in state 4 and the second in
+    /// state 5. Any third letter is written in state 9, a fourth in state 10 and so on.
+    fn zero(&mut self) -> bool {
+        // 0
+        call_stack::state(0);
+        self.main_tape.right();
+        if self.main_tape.has_halted() {
+            return false;
+        }
+        // 1
+        call_stack::state(1);
+        match self.main_tape.read() {
+            TapeValue::Empty => {
+                unreachable!()
+            }
+            TapeValue::Value(symbol) if symbol.letter().is_some() => {
+                // 2
+                call_stack::state(2);
+                self.aux_tape.right();
+                if self.aux_tape.has_halted() {
+                    return false;
+                }
+                // 3
+                call_stack::state(3);
+                let letter = if let Some(letter) = self.pick() {
+                    letter
+                } else {
+                    trace!("Guesser: certificate too short");
+                    return false;
+                };
+                // Write
+                let index = M::Letter::symbols()
+                    .iter()
+                    .position(|symbol| symbol == &letter)
+                    .unwrap();
+                call_stack::state(match index {
+                    0 => 4,
+                    1 => 5,
+                    _ => 7 + u32::try_from(index).unwrap(),
+                });
+                self.aux_tape.write(TapeValue::Value(letter));
+                // 0
+                self.zero()
+            }
+            TapeValue::Value(_) => {
+                if !self.is_certificate_exhausted() {
+                    trace!("Guesser: certificate too long");
+                    return false;
+                }
+                // 6
+                self.six()
+            }
+        }
+    }
+
+    fn six(&mut self) -> bool {
+        // 6
+        call_stack::state(6);
+        self.aux_tape.left();
+        if self.aux_tape.has_halted() {
+            return false;
+        }
+        // 7
+        call_stack::state(7);
+        match self.aux_tape.read() {
+            TapeValue::Value(_) => {
+                // 6
+                self.six()
+            }
+            TapeValue::Empty => {
+                // 8
+                call_stack::state(8);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::TapeBuilder,
+        chooser::explore,
+        test_support::{words, Strand},
+    };
+
+    /// Guesser on `word` ended by a separator after an empty head, as `M2::input_for` lays it out
+    fn guesser(word: &str) -> Guesser<Strand> {
+        Guesser::new(
+            TapeBuilder::new()
+                .head()
+                .blank()
+                .word(word)
+                .separator()
+                .build(),
+            TapeBuilder::new().head().blank().build(),
+        )
+    }
+
+    fn aux_word<T: Alphabet>(aux_tape: &Tape<T>) -> String {
+        aux_tape.data()[1..]
+            .iter()
+            .map_while(|value| match value {
+                TapeValue::Value(letter) => Some(letter.to_char()),
+                TapeValue::Empty => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bases() {
+        let mut guesser = guesser("gattaca");
+        assert!(guesser.run());
+        let word = aux_word(guesser.destroy().aux_tape());
+        assert_eq!(word.len(), 7);
+        assert!(word.chars().all(|char| "acgt".contains(char)));
+    }
+
+    #[test]
+    fn branches() {
+        // One branch per word, rather than one per sequence of binary choices
+        let mut guessed = vec![];
+        let exploration = explore(false, || {
+            let mut guesser = guesser("ga");
+            let output = guesser.run();
+            guessed.push(aux_word(guesser.destroy().aux_tape()));
+            output
+        });
+        assert_eq!(exploration.branches, 16);
+        assert_eq!(exploration.accepted, Some(vec![0, 0]));
+        assert_eq!(guessed, words("acgt", 2..3));
+    }
+}
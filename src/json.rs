@@ -0,0 +1,243 @@
+//! Serialization with `serde`, behind the `serde` feature. The JSON shape is:
+//!
+//! - `MainValue` and `AuxValue`: their character, `"a"`, `"b"` or `"#"`
+//! - `TapeValue`: the character of its symbol, or `"_"` if empty
+//! - `TapeConstructor`: `{"value": cell}` or `{"head": cell}`
+//! - `Tape`: `{"cells": "#ab#", "head": 0, "left_end": "grow", "end_markers": false,
+//!   "crashed": false, "violated_bound": false}`, with a character per cell as for `TapeValue`.
+//!   `left_end` is `"grow"`, `"stay"` or `"crash"`, and every field but `cells` and `head` can be
+//!   left out for its default. Restored tapes count no steps, and track space and crossings from
+//!   their head as new tapes do.
+//! - `DestroyOutput`: `{"main_tape": tape, "aux_tape": tape}`
+//! - `Outcome`: `"accept"`, `"reject"`, `"crash"`, `"bound_violation"` or
+//!   `{"looping": {"cycle": 3}}`
+
+use serde::{
+    de::{self, Deserializer},
+    ser::Serializer,
+    Deserialize, Serialize,
+};
+
+use crate::{
+    alphabet::Alphabet,
+    machine::{AuxValue, MainValue},
+    tape::{LeftEnd, Tape, TapeConstructor, TapeValue},
+};
+
+fn serialize_char<S: Serializer>(char: char, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(char.encode_utf8(&mut [0; 4]))
+}
+
+/// Cell of a single character string
+fn deserialize_cell<'de, T: Alphabet, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<TapeValue<T>, D::Error> {
+    let string = String::deserialize(deserializer)?;
+    let mut chars = string.chars();
+    match (chars.next(), chars.next()) {
+        (Some(char), None) => cell(char),
+        _ => Err(de::Error::custom(format!(
+            "expected a single character, found `{}`",
+            string
+        ))),
+    }
+}
+
+fn cell<T: Alphabet, E: de::Error>(char: char) -> Result<TapeValue<T>, E> {
+    TapeValue::from_char(char)
+        .ok_or_else(|| E::custom(format!("`{}` is not a symbol of the alphabet", char)))
+}
+
+impl Serialize for MainValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_char(self.to_char(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MainValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match deserialize_cell(deserializer)? {
+            TapeValue::Value(symbol) => Ok(symbol),
+            TapeValue::Empty => Err(de::Error::custom("expected a symbol, found an empty cell")),
+        }
+    }
+}
+
+impl Serialize for AuxValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_char(self.to_char(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AuxValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match deserialize_cell(deserializer)? {
+            TapeValue::Value(symbol) => Ok(symbol),
+            TapeValue::Empty => Err(de::Error::custom("expected a symbol, found an empty cell")),
+        }
+    }
+}
+
+impl<T: Alphabet> Serialize for TapeValue<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, T: Alphabet> Deserialize<'de> for TapeValue<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_cell(deserializer)
+    }
+}
+
+/// Shape of a serialized `Tape`
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TapeShape {
+    cells: String,
+    head: usize,
+    #[serde(default)]
+    left_end: LeftEnd,
+    #[serde(default)]
+    end_markers: bool,
+    #[serde(default)]
+    crashed: bool,
+    #[serde(default)]
+    violated_bound: bool,
+}
+
+impl<T: Alphabet> Serialize for Tape<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TapeShape {
+            cells: self.data().iter().map(ToString::to_string).collect(),
+            head: self.head(),
+            left_end: self.left_end(),
+            end_markers: self.has_end_markers(),
+            crashed: self.has_crashed(),
+            violated_bound: self.has_violated_bound(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de, T: Alphabet> Deserialize<'de> for Tape<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let shape = TapeShape::deserialize(deserializer)?;
+        let data = shape
+            .cells
+            .chars()
+            .enumerate()
+            .map(|(index, char)| {
+                cell(char).map(|cell| {
+                    if index == shape.head {
+                        TapeConstructor::Head(cell)
+                    } else {
+                        TapeConstructor::Value(cell)
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if shape.head >= data.len() {
+            return Err(de::Error::custom(format!(
+                "head {} must be on one of the {} cells",
+                shape.head,
+                data.len()
+            )));
+        }
+
+        let tape = Self::new(data, None)
+            .with_left_end(shape.left_end)
+            .with_halted(shape.crashed, shape.violated_bound);
+        Ok(if shape.end_markers {
+            tape.with_end_markers()
+        } else {
+            tape
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        m1::M1,
+        machine::{Outcome, TuringMachine},
+    };
+    use serde_json::{from_str, json, to_string, to_value};
+
+    #[test]
+    fn shape() {
+        let (main_tape, aux_tape) = M1::input_for("ab", "b", None);
+        let main_tape = main_tape.with_left_end(LeftEnd::Crash);
+        assert_eq!(
+            to_value(&main_tape).unwrap(),
+            json!({
+                "cells": "#ab#",
+                "head": 0,
+                "left_end": "crash",
+                "end_markers": false,
+                "crashed": false,
+                "violated_bound": false,
+            })
+        );
+        assert_eq!(
+            to_value(&main_tape.as_constructor()[..2]).unwrap(),
+            json!([{"head": "#"}, {"value": "a"}])
+        );
+        assert_eq!(
+            to_value([Outcome::BoundViolation, Outcome::Looping { cycle: 3 }]).unwrap(),
+            json!(["bound_violation", {"looping": {"cycle": 3}}])
+        );
+
+        let mut m1 = M1::new(main_tape, aux_tape);
+        assert!(m1.run());
+        let destroy = to_value(m1.destroy()).unwrap();
+        assert_eq!(destroy["main_tape"]["head"], 3);
+        assert_eq!(destroy["aux_tape"]["cells"], "_b_");
+    }
+
+    #[test]
+    fn round_trip() {
+        let (main_tape, mut aux_tape) = M1::input_for("ab", "b", None);
+        let main_tape = main_tape.with_end_markers();
+        aux_tape = aux_tape.with_left_end(LeftEnd::Crash);
+        aux_tape.left();
+        assert!(aux_tape.has_crashed());
+        assert_eq!(
+            from_str::<Tape<MainValue>>(&to_string(&main_tape).unwrap()).unwrap(),
+            main_tape
+        );
+        assert_eq!(
+            from_str::<Tape<AuxValue>>(&to_string(&aux_tape).unwrap()).unwrap(),
+            aux_tape
+        );
+
+        for outcome in [
+            Outcome::Accept,
+            Outcome::Crash,
+            Outcome::Looping { cycle: 7 },
+        ] {
+            assert_eq!(
+                from_str::<Outcome>(&to_string(&outcome).unwrap()).unwrap(),
+                outcome
+            );
+        }
+        let values = [TapeValue::Empty, TapeValue::Value(MainValue::Hash)];
+        assert_eq!(
+            from_str::<[TapeValue<MainValue>; 2]>(&to_string(&values).unwrap()).unwrap(),
+            values
+        );
+        assert_eq!(from_str::<AuxValue>(r#""b""#).unwrap(), AuxValue::B);
+    }
+
+    #[test]
+    fn invalid() {
+        let error = |json: &str| from_str::<Tape<AuxValue>>(json).unwrap_err().to_string();
+        assert!(error(r#"{"cells": "_a#", "head": 0}"#).contains("`#` is not a symbol"));
+        assert!(error(r#"{"cells": "_a", "head": 2}"#).contains("head 2 must be on one of the 2"));
+        assert!(error(r#"{"cells": "_a", "head": 0, "steps": 3}"#).contains("unknown field"));
+        assert!(from_str::<Tape<AuxValue>>(r#"{"cells": "_a", "head": 1}"#).is_ok());
+        assert!(from_str::<AuxValue>(r#""ab""#).is_err());
+        assert!(from_str::<AuxValue>(r#""_""#).is_err());
+    }
+}
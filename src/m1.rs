@@ -1,273 +1,158 @@
-use log::trace;
+use std::{cell::RefCell, rc::Rc};
 
 use crate::{
-    machine::{AuxValue, DestroyOutput, MainValue, TuringMachine},
-    tape::{Tape, TapeConstructor, TapeValue},
+    builder::TapeBuilder,
+    machine::{AuxValue, MainValue},
+    subsequence::Subsequence,
+    table::{Rule, Table},
+    tape::{
+        Move::{Left, Right, Stay},
+        Tape, TapeValue,
+    },
 };
 
-pub struct M1 {
-    main: Tape<MainValue>,
-    aux: Tape<AuxValue>,
-    // steps_ran: Rc<RefCell<u64>>,
-}
-
-impl TuringMachine for M1 {
-    // pub fn steps_ran(&self) -> u64 {
-    //     *self.steps_ran.borrow()
-    //     // self.main.steps_ran()
-    // }
-
-    // pub fn increment_steps_ran(&self) {
-    //     *self.steps_ran.borrow_mut() += 1;
-    // }
-
-    fn new(main_tape: Tape<MainValue>, aux_tape: Tape<AuxValue>) -> Self {
-        {
-            let mut main_tape = main_tape.clone();
-            assert_eq!(main_tape.read(), TapeValue::Value(MainValue::Hash));
-            let mut found_a_b = false;
-            loop {
-                main_tape.right();
-                match main_tape.read() {
-                    TapeValue::Value(MainValue::A | MainValue::B) => {
-                        found_a_b = true;
-                    }
-                    TapeValue::Value(MainValue::Hash) | TapeValue::Empty => {
-                        break;
-                    }
-                };
-            }
-            assert!(found_a_b);
-        }
-
-        {
-            let mut aux_tape = aux_tape.clone();
-
-            // All cells on the left of the head and the head itself must be empty
-            for cell in aux_tape.as_constructor().iter() {
-                match cell {
-                    TapeConstructor::Head(TapeValue::Empty) => {
-                        break;
-                    }
-                    TapeConstructor::Value(TapeValue::Empty) => {
-                        continue;
-                    }
-                    TapeConstructor::Value(TapeValue::Value(_)) => {
-                        panic!("Aux tape must only have empty cells on the left of the head");
-                    }
-                    TapeConstructor::Head(TapeValue::Value(_)) => {
-                        panic!("Head must be empty")
-                    }
-                };
-            }
-
-            let mut found_a_b = false;
-            loop {
-                aux_tape.right();
-                match aux_tape.read() {
-                    TapeValue::Value(AuxValue::A | AuxValue::B) => {
-                        found_a_b = true;
-                    }
-                    TapeValue::Empty => {
-                        break;
-                    }
-                };
-            }
-            assert!(found_a_b);
-
-            // All cells on the right of the word must be empty
-            while !aux_tape.is_at_end() {
-                aux_tape.right();
-                assert_eq!(aux_tape.read(), TapeValue::Empty);
-            }
-        }
-
-        // let steps_ran = Rc::new(RefCell::new(0));
-        Self {
-            main: main_tape,
-            aux: aux_tape,
-            // steps_ran,
-        }
-    }
-
-    #[must_use]
-    fn run(&mut self) -> bool {
-        self.zero()
-    }
-
-    fn destroy(self) -> DestroyOutput {
-        DestroyOutput::new(self.main, self.aux)
-    }
-}
-
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
-
-//     #[test]
-//     fn new() {
-//         M1::new(
-//             vec![
-//                 TapeConstructor::Head(TapeValue::Value(MainValue::Hash)),
-//                 TapeConstructor::Value(TapeValue::Value(MainValue::A)),
-//                 TapeConstructor::Value(TapeValue::Value(MainValue::B)),
-//                 TapeConstructor::Value(TapeValue::Value(MainValue::B)),
-//                 TapeConstructor::Value(TapeValue::Value(MainValue::A)),
-//                 TapeConstructor::Value(TapeValue::Value(MainValue::Hash)),
-//             ],
-//             vec![
-//                 TapeConstructor::Head(TapeValue::Empty),
-//                 TapeConstructor::Value(TapeValue::Value(AuxValue::A)),
-//                 TapeConstructor::Value(TapeValue::Value(AuxValue::B)),
-//                 TapeConstructor::Value(TapeValue::Empty),
-//             ],
-//         );
-//     }
-// }
+/// Accepts if the aux word is a subsequence of the main word, see `Subsequence`
+pub type M1 = Subsequence<MainValue>;
 
-// Run
 impl M1 {
-    /// 0
-    fn zero(&mut self) -> bool {
-        // 0
-        trace!("M1-0");
-        self.aux.right();
-        // 1
-        trace!("M1-1");
-        let read = self.aux.read();
-        match read {
-            TapeValue::Value(AuxValue::A) => {
-                // 2
-                self.two()
-            }
-            TapeValue::Value(AuxValue::B) => {
-                // 6
-                self.six()
-            }
-            TapeValue::Empty => {
-                // 4
-                self.four()
-            }
-        }
-    }
-
-    /// 2
-    fn two(&mut self) -> bool {
-        // 2
-        trace!("M1-2");
-        self.main.right();
-        // 3
-        trace!("M1-3");
-        let read = self.main.read();
-        match read {
-            TapeValue::Value(MainValue::A) => {
-                // 0
-                self.zero()
-            }
-            TapeValue::Value(MainValue::B) => {
-                // 2
-                self.two()
-            }
-            TapeValue::Empty | TapeValue::Value(MainValue::Hash) => {
-                // 8
-                self.eight()
-            }
-        }
+    /// Main tape of `main` between hashes with the head on the first, and aux tape of `aux` after
+    /// an empty head
+    pub fn input_for(
+        main: &str,
+        aux: &str,
+        steps_ran: Option<Rc<RefCell<u64>>>,
+    ) -> (Tape<MainValue>, Tape<AuxValue>) {
+        (
+            TapeBuilder::new()
+                .steps_ran(steps_ran.clone())
+                .head()
+                .separator()
+                .word(main)
+                .separator()
+                .build(),
+            TapeBuilder::new()
+                .steps_ran(steps_ran)
+                .head()
+                .blank()
+                .word(aux)
+                .blank()
+                .build(),
+        )
     }
 
-    /// 4
-    fn four(&mut self) -> bool {
-        // 4
-        trace!("M1-4");
-        self.aux.left();
-        // 5
-        trace!("M1-5");
-        let read = self.aux.read();
-        match read {
-            TapeValue::Value(AuxValue::A | AuxValue::B) => {
-                // 4
-                self.four()
-            }
-            TapeValue::Empty => {
-                // 11
-                self.eleven()
-            }
+    /// `M1` as a table machine. Its states are the states of `M1` that read a cell, with each
+    /// move folded into the transition before it.
+    pub fn table() -> Table<MainValue, AuxValue> {
+        let main_a = Some(TapeValue::Value(MainValue::A));
+        let main_b = Some(TapeValue::Value(MainValue::B));
+        let main_hash = Some(TapeValue::Value(MainValue::Hash));
+        let aux_a = Some(TapeValue::Value(AuxValue::A));
+        let aux_b = Some(TapeValue::Value(AuxValue::B));
+        let main_empty = Some(TapeValue::Empty);
+        let aux_empty = Some(TapeValue::Empty);
+
+        let mut rules = vec![
+            // Next letter
+            Rule::new(0, (None, None), 1, (None, None), (Stay, Right)),
+            Rule::new(1, (None, aux_a.clone()), 3, (None, None), (Right, Stay)),
+            Rule::new(1, (None, aux_b.clone()), 7, (None, None), (Right, Stay)),
+            Rule::new(1, (None, aux_empty.clone()), 5, (None, None), (Stay, Left)),
+            // Scan for a
+            Rule::new(3, (main_a.clone(), None), 1, (None, None), (Stay, Right)),
+            Rule::new(3, (main_b.clone(), None), 3, (None, None), (Right, Stay)),
+            // Scan for b
+            Rule::new(7, (main_b.clone(), None), 1, (None, None), (Stay, Right)),
+            Rule::new(7, (main_a.clone(), None), 7, (None, None), (Right, Stay)),
+            // Rewind the aux tape, then skip the rest of the word
+            Rule::new(
+                5,
+                (None, aux_empty.clone()),
+                12,
+                (None, None),
+                (Right, Stay),
+            ),
+            Rule::new(12, (main_a, None), 12, (None, None), (Right, Stay)),
+            Rule::new(12, (main_b, None), 12, (None, None), (Right, Stay)),
+            // Rewind the aux tape and reject from state 10, which has no transitions
+            Rule::new(9, (None, aux_empty.clone()), 10, (None, None), (Stay, Stay)),
+        ];
+        for end in [main_hash, main_empty] {
+            rules.push(Rule::new(
+                3,
+                (end.clone(), None),
+                9,
+                (None, None),
+                (Stay, Left),
+            ));
+            rules.push(Rule::new(
+                7,
+                (end.clone(), None),
+                9,
+                (None, None),
+                (Stay, Left),
+            ));
+            rules.push(Rule::new(12, (end, None), 13, (None, None), (Stay, Stay)));
         }
-    }
-
-    /// 6
-    fn six(&mut self) -> bool {
-        // 6
-        trace!("M1-6");
-        self.main.right();
-        // 7
-        trace!("M1-7");
-        let read = self.main.read();
-        match read {
-            TapeValue::Value(MainValue::A) => {
-                // 6
-                self.six()
-            }
-            TapeValue::Value(MainValue::B) => {
-                // 0
-                self.zero()
-            }
-            TapeValue::Empty | TapeValue::Value(MainValue::Hash) => {
-                // 8
-                self.eight()
-            }
+        for letter in [aux_a, aux_b] {
+            rules.push(Rule::new(
+                5,
+                (None, letter.clone()),
+                5,
+                (None, None),
+                (Stay, Left),
+            ));
+            rules.push(Rule::new(9, (None, letter), 9, (None, None), (Stay, Left)));
         }
+        Table::new("M1 table", 0, 13, rules)
     }
+}
 
-    /// 8
-    fn eight(&mut self) -> bool {
-        // 8
-        trace!("M1-8");
-        self.aux.left();
-        // 9
-        trace!("M1-9");
-        let read = self.aux.read();
-        match read {
-            TapeValue::Value(AuxValue::A | AuxValue::B) => {
-                // 8
-                self.eight()
-            }
-            TapeValue::Empty => {
-                // 10
-                trace!("M1-10");
-                // self.increment_steps_ran();
-                false
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        machine::{Outcome, TuringMachine},
+        tape::LeftEnd,
+        test_support::words,
+    };
+
+    #[test]
+    fn table() {
+        let table = M1::table();
+        assert!(table.is_deterministic());
+        for main in words("ab", 1..5) {
+            for aux in words("ab", 1..4) {
+                let (mut main_tape, mut aux_tape) = M1::input_for(&main, &aux, None);
+                let mut m1_main_tape = main_tape.clone();
+                let mut m1_aux_tape = aux_tape.clone();
+
+                assert_eq!(
+                    table.run_on(&mut main_tape, &mut aux_tape),
+                    M1::run_on(&mut m1_main_tape, &mut m1_aux_tape),
+                    "{} {}",
+                    main,
+                    aux
+                );
+                assert_eq!(main_tape.head(), m1_main_tape.head());
+                assert_eq!(aux_tape.head(), m1_aux_tape.head());
             }
         }
     }
 
-    /// 11
-    fn eleven(&mut self) -> bool {
-        // 11
-        trace!("M1-11");
-        self.main.right();
-        // 12
-        trace!("M1-12");
-        let read = self.main.read();
-        match read {
-            TapeValue::Value(MainValue::A | MainValue::B) => {
-                // 11
-                self.eleven()
-            }
-            TapeValue::Empty | TapeValue::Value(MainValue::Hash) => {
-                // 13
-                trace!("M1-13");
-                // self.increment_steps_ran();
-                true
-            }
-        }
+    #[test]
+    fn one_way_infinite() {
+        // M1 rewinds the aux tape onto its first cell but never past it
+        let (main_tape, aux_tape) = M1::input_for("abba", "ab", None);
+        let mut main_tape = main_tape.with_left_end(LeftEnd::Crash);
+        let mut aux_tape = aux_tape.with_left_end(LeftEnd::Crash);
+        assert_eq!(
+            M1::outcome_on(&mut main_tape, &mut aux_tape),
+            Outcome::Accept
+        );
+        let (mut main_tape, _) = M1::input_for("ba", "ab", None);
+        assert_eq!(
+            M1::table().outcome_on(&mut main_tape, &mut aux_tape),
+            Outcome::Reject
+        );
     }
-
-    // #[must_use]
-    // pub fn run(&mut self) -> bool {
-    //     assert_eq!(self.steps_ran(), 0, "Machine cannot be ran more than once");
-
-    //     // 0
-    //     self.zero()
-    // }
 }
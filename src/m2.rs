@@ -1,167 +1,101 @@
-use log::trace;
+use std::{cell::RefCell, rc::Rc};
 
 use crate::{
-    machine::{choose, AuxValue, DestroyOutput, MainValue, TuringMachine},
-    tape::{Tape, TapeConstructor, TapeValue},
+    builder::TapeBuilder,
+    guesser::Guesser,
+    machine::{AuxValue, MainValue},
+    table::{Rule, Table},
+    tape::{
+        Move::{Left, Right, Stay},
+        Tape, TapeValue,
+    },
 };
 
-pub struct M2 {
-    main_tape: Tape<MainValue>,
-    aux_tape: Tape<AuxValue>,
-}
-
-impl TuringMachine for M2 {
-    fn new(main_tape: Tape<MainValue>, aux_tape: Tape<AuxValue>) -> Self {
-        {
-            let mut main_tape = main_tape.clone();
-            // All cells to the left of the head and the head itself must be empty
-            for cell in main_tape.as_constructor().iter() {
-                match cell {
-                    TapeConstructor::Head(TapeValue::Empty) => {
-                        break;
-                    }
-                    TapeConstructor::Value(TapeValue::Empty) => {
-                        continue;
-                    }
-                    TapeConstructor::Value(TapeValue::Value(_)) => {
-                        panic!("M2 main tape must only have empty cells on the left of the head");
-                    }
-                    TapeConstructor::Head(TapeValue::Value(_)) => {
-                        panic!("M2 head must be empty")
-                    }
-                };
-            }
-
-            // Head must be followed by a non-empty word
-            let mut found_a_b = false;
-            loop {
-                main_tape.right();
-                match main_tape.read() {
-                    TapeValue::Value(MainValue::A | MainValue::B) => {
-                        found_a_b = true;
-                    }
-                    TapeValue::Value(MainValue::Hash) => {
-                        break;
-                    }
-                    TapeValue::Empty => {
-                        panic!("M2 main tape word must be followed by a hash");
-                    }
-                };
-            }
-            assert!(found_a_b);
-        }
-
-        {
-            // Aux tape must be blank
-            let aux_tape = aux_tape.clone();
-            for cell in aux_tape.as_constructor().iter() {
-                match cell {
-                    TapeConstructor::Head(TapeValue::Value(_))
-                    | TapeConstructor::Value(TapeValue::Value(_)) => {
-                        panic!("M2 aux tape must be blank");
-                    }
-                    _ => {}
-                };
-            }
-        }
-
-        Self {
-            main_tape,
-            aux_tape,
-        }
-    }
+/// Guesses a word as long as the main word, see `Guesser`
+pub type M2 = Guesser<MainValue>;
 
-    fn run(&mut self) -> bool {
-        self.zero()
+impl M2 {
+    /// Main tape of `word` followed by a hash after an empty head, and a blank aux tape
+    pub fn input_for(
+        word: &str,
+        steps_ran: Option<Rc<RefCell<u64>>>,
+    ) -> (Tape<MainValue>, Tape<AuxValue>) {
+        (
+            TapeBuilder::new()
+                .steps_ran(steps_ran.clone())
+                .head()
+                .blank()
+                .word(word)
+                .separator()
+                .build(),
+            TapeBuilder::new()
+                .steps_ran(steps_ran)
+                .head()
+                .blank()
+                .build(),
+        )
     }
 
-    fn destroy(self) -> DestroyOutput {
-        DestroyOutput::new(self.main_tape, self.aux_tape)
-    }
-}
+    /// `M2` as a nondeterministic table machine, with states 4 and 5 folded into the two
+    /// transitions out of state 3
+    pub fn table() -> Table<MainValue, AuxValue> {
+        let aux_a = Some(TapeValue::Value(AuxValue::A));
+        let aux_b = Some(TapeValue::Value(AuxValue::B));
 
-// Run
-impl M2 {
-    fn zero(&mut self) -> bool {
-        // 0
-        trace!("M2: 0");
-        self.main_tape.right();
-        // 1
-        trace!("M2: 1");
-        let read = self.main_tape.read();
-        match read {
-            TapeValue::Empty => {
-                unreachable!()
-            }
-            TapeValue::Value(MainValue::A | MainValue::B) => {
-                // 2
-                trace!("M2: 2");
-                self.aux_tape.right();
-                // 3
-                trace!("M2: 3");
-                let pick = choose();
-                if pick {
-                    // 4
-                    trace!("M2: 4");
-                    self.aux_tape.write(TapeValue::Value(AuxValue::A));
-                    // 0
-                    self.zero()
-                } else {
-                    // 5
-                    trace!("M2: 5");
-                    self.aux_tape.write(TapeValue::Value(AuxValue::B));
-                    // 0
-                    self.zero()
-                }
-            }
-            TapeValue::Value(MainValue::Hash) => {
-                // 6
-                self.six()
-            }
+        let mut rules = vec![
+            Rule::new(0, (None, None), 1, (None, None), (Right, Stay)),
+            Rule::new(
+                1,
+                (Some(TapeValue::Value(MainValue::Hash)), None),
+                7,
+                (None, None),
+                (Stay, Left),
+            ),
+            Rule::new(
+                7,
+                (None, Some(TapeValue::Empty)),
+                8,
+                (None, None),
+                (Stay, Stay),
+            ),
+        ];
+        for letter in [MainValue::A, MainValue::B] {
+            rules.push(Rule::new(
+                1,
+                (Some(TapeValue::Value(letter)), None),
+                3,
+                (None, None),
+                (Stay, Right),
+            ));
         }
-    }
-
-    fn six(&mut self) -> bool {
-        // 6
-        trace!("M2: 6");
-        self.aux_tape.left();
-        // 7
-        trace!("M2: 7");
-        let read = self.aux_tape.read();
-        match read {
-            TapeValue::Value(AuxValue::A | AuxValue::B) => {
-                // 6
-                self.six()
-            }
-            TapeValue::Empty => {
-                // 8
-                trace!("M2: 8");
-                true
-            }
+        for letter in [aux_a, aux_b] {
+            rules.push(Rule::new(
+                3,
+                (None, None),
+                0,
+                (None, letter.clone()),
+                (Stay, Stay),
+            ));
+            rules.push(Rule::new(7, (None, letter), 7, (None, None), (Stay, Left)));
         }
+        Table::new("M2 table", 0, 8, rules)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        chooser::{with_chooser, ScriptedChooser},
+        machine::{certificate_tape, TuringMachine, Verifier},
+    };
+    use std::{cell::RefCell, rc::Rc};
 
     #[test]
     fn run() {
-        let main_tape = Tape::new(
-            vec![
-                TapeConstructor::Head(TapeValue::Empty),
-                TapeConstructor::Value(TapeValue::Value(MainValue::A)),
-                TapeConstructor::Value(TapeValue::Value(MainValue::B)),
-                TapeConstructor::Value(TapeValue::Value(MainValue::A)),
-                TapeConstructor::Value(TapeValue::Value(MainValue::B)),
-                TapeConstructor::Value(TapeValue::Value(MainValue::Hash)),
-            ],
-            None,
-        );
-        let aux_tape = Tape::new(vec![TapeConstructor::Head(TapeValue::Empty)], None);
+        let (main_tape, aux_tape) = M2::input_for("abab", None);
         let mut m2 = M2::new(main_tape.clone(), aux_tape);
-        m2.run();
+        assert!(m2.run());
         let output = m2.destroy();
 
         assert_eq!(output.main_tape().data(), main_tape.data());
@@ -170,15 +104,73 @@ mod tests {
 
         assert_eq!(output.aux_tape().read(), TapeValue::Empty);
         assert_eq!(output.aux_tape().head(), 0);
-        for index in 1..5 {
-            match output.aux_tape().data()[index] {
+        let aux = output.aux_tape().canonical();
+        assert_eq!(aux.head(), -1);
+        assert_eq!(aux.cells().len(), 4);
+        for cell in aux.cells() {
+            match cell {
                 TapeValue::Value(AuxValue::A | AuxValue::B) => {}
-                _ => panic!(),
+                TapeValue::Empty => panic!(),
             };
         }
-        assert_eq!(
-            output.aux_tape().data().get(5).unwrap_or(&TapeValue::Empty),
-            &TapeValue::Empty
+    }
+
+    #[test]
+    fn run_with_certificate() {
+        let (main_tape, aux_tape) = M2::input_for("aba", None);
+        let certificate = [AuxValue::B, AuxValue::B, AuxValue::A];
+
+        let mut m2 = M2::with_certificate(
+            main_tape.clone(),
+            aux_tape.clone(),
+            certificate_tape(&certificate, None),
+        );
+        assert!(m2.run());
+        let output = m2.destroy();
+        assert_eq!(output.aux_tape().head(), 0);
+        for (index, value) in certificate.iter().enumerate() {
+            assert_eq!(
+                output.aux_tape().data()[index + 1],
+                TapeValue::Value(value.clone())
+            );
+        }
+
+        // Too short
+        let mut m2 = M2::with_certificate(
+            main_tape.clone(),
+            aux_tape.clone(),
+            certificate_tape(&certificate[..2], None),
         );
+        assert!(!m2.run());
+
+        // Too long
+        let mut m2 = M2::with_certificate(
+            main_tape,
+            aux_tape,
+            certificate_tape(&[AuxValue::A, AuxValue::A, AuxValue::A, AuxValue::A], None),
+        );
+        assert!(!m2.run());
+    }
+
+    #[test]
+    fn table() {
+        let table = M2::table();
+        assert!(!table.is_deterministic());
+        for script in [vec![0, 0, 0], vec![1, 0, 1], vec![1, 1, 0]] {
+            let (mut main_tape, mut aux_tape) = M2::input_for("aba", None);
+            let mut m2_main_tape = main_tape.clone();
+            let mut m2_aux_tape = aux_tape.clone();
+
+            assert!(with_chooser(
+                Rc::new(RefCell::new(ScriptedChooser::new(script.clone()))),
+                || table.run_on(&mut main_tape, &mut aux_tape)
+            ));
+            assert!(with_chooser(
+                Rc::new(RefCell::new(ScriptedChooser::new(script))),
+                || M2::run_on(&mut m2_main_tape, &mut m2_aux_tape)
+            ));
+            assert_eq!(main_tape, m2_main_tape);
+            assert_eq!(aux_tape, m2_aux_tape);
+        }
     }
 }
@@ -1,195 +1,52 @@
-use log::trace;
+use std::{cell::RefCell, rc::Rc};
 
 use crate::{
-    m1::M1,
-    m2::M2,
-    machine::{choose, AuxValue, DestroyOutput, MainValue, TuringMachine},
-    tape::{Tape, TapeConstructor, TapeValue},
+    builder::TapeBuilder,
+    common_subsequence::CommonSubsequence,
+    machine::{AuxValue, MainValue},
+    tape::Tape,
 };
 
-pub struct M3 {
-    main_tape: Tape<MainValue>,
-    aux_tape: Tape<AuxValue>,
-    substring: Option<String>,
-}
-
-impl TuringMachine for M3 {
-    fn new(main_tape: Tape<MainValue>, aux_tape: Tape<AuxValue>) -> Self {
-        {
-            // abab#abab#abab
-            let mut main_tape = main_tape.clone();
-            // All cells to the left of the head and the head itself must be empty
-            for cell in main_tape.as_constructor().iter() {
-                match cell {
-                    TapeConstructor::Value(TapeValue::Empty) => {
-                        continue;
-                    }
-                    TapeConstructor::Value(TapeValue::Value(_)) => {
-                        panic!("M3 main tape must have only empty cells on the left of the head");
-                    }
-                    TapeConstructor::Head(TapeValue::Empty) => {
-                        break;
-                    }
-                    TapeConstructor::Head(_) => {
-                        panic!("M3 main tape head must be empty");
-                    }
-                };
-            }
-
-            let mut found_empty = false;
-            while !found_empty {
-                let mut found_a_b = false;
-                loop {
-                    main_tape.right();
-                    match main_tape.read() {
-                        TapeValue::Value(MainValue::A | MainValue::B) => {
-                            found_a_b = true;
-                            continue;
-                        }
-                        TapeValue::Value(MainValue::Hash) => {
-                            break;
-                        }
-                        TapeValue::Empty => {
-                            found_empty = true;
-                            break;
-                        }
-                    }
-                }
-                assert!(found_a_b);
-            }
-
-            // After first empty after words, must only be empty cells
-            while !main_tape.is_at_end() {
-                main_tape.right();
-                assert!(main_tape.read() == TapeValue::Empty)
-            }
-        }
-
-        {
-            // Aux tape must be blank
-            let aux_tape = aux_tape.clone();
-            for cell in aux_tape.as_constructor().iter() {
-                match cell {
-                    TapeConstructor::Head(TapeValue::Value(_))
-                    | TapeConstructor::Value(TapeValue::Value(_)) => {
-                        panic!("M3 aux tape must be blank");
-                    }
-                    _ => {}
-                };
-            }
-        }
-
-        Self {
-            main_tape,
-            aux_tape,
-            substring: None,
-        }
-    }
-
-    #[must_use]
-    fn run(&mut self) -> bool {
-        self.zero()
-    }
-
-    fn destroy(self) -> DestroyOutput {
-        DestroyOutput::new(self.main_tape, self.aux_tape)
-    }
-}
+/// Guesses a word as long as the first word and accepts if it is a subsequence of every other
+/// word, see `CommonSubsequence`
+pub type M3 = CommonSubsequence<MainValue>;
 
 impl M3 {
-    pub fn substring(&self) -> &str {
-        self.substring.as_deref().unwrap()
-    }
-}
-
-// Run
-impl M3 {
-    fn zero(&mut self) -> bool {
-        // 0
-        trace!("M3: 0");
-
-        let mut m2 = M2::new(self.main_tape.clone(), self.aux_tape.clone());
-        m2.run();
-        let destroy = m2.destroy();
-        self.main_tape = destroy.main_tape().clone();
-        self.aux_tape = destroy.aux_tape().clone();
-
-        self.substring = {
-            let mut aux_tape = destroy.aux_tape().clone();
-            let mut substring = String::new();
-            loop {
-                aux_tape.right();
-                match aux_tape.read() {
-                    TapeValue::Value(AuxValue::A) => {
-                        substring.push('a');
-                    }
-                    TapeValue::Value(AuxValue::B) => {
-                        substring.push('b');
-                    }
-                    TapeValue::Empty => {
-                        break;
-                    }
-                };
-            }
-            assert!(!substring.is_empty());
-            Some(substring)
-        };
-
-        // 1
-        self.one()
-    }
-
-    fn one(&mut self) -> bool {
-        // 1
-        trace!("M3: 1");
-
-        let mut m1 = M1::new(self.main_tape.clone(), self.aux_tape.clone());
-        let output = m1.run();
-        let destroy = m1.destroy();
-        self.main_tape = destroy.main_tape().clone();
-        self.aux_tape = destroy.aux_tape().clone();
-
-        if output {
-            // 3
-            trace!("M3: 3");
-            let read = self.main_tape.read();
-            match read {
-                TapeValue::Empty => {
-                    // 4
-                    trace!("M3: 4");
-                    true
-                }
-                TapeValue::Value(MainValue::Hash) => {
-                    // 1
-                    self.one()
-                }
-                TapeValue::Value(_) => {
-                    unreachable!()
-                }
-            }
-        } else {
-            // 2
-            trace!("M3: 2");
-            false
-        }
+    /// Main tape of `words` separated by hashes after an empty head, and a blank aux tape
+    pub fn input_for(
+        words: &[&str],
+        steps_ran: Option<Rc<RefCell<u64>>>,
+    ) -> (Tape<MainValue>, Tape<AuxValue>) {
+        (
+            TapeBuilder::new()
+                .steps_ran(steps_ran.clone())
+                .head()
+                .blank()
+                .words(words)
+                .build(),
+            TapeBuilder::new()
+                .steps_ran(steps_ran)
+                .head()
+                .blank()
+                .build(),
+        )
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    fn str_to_m3_main_tape(s: &str) -> Tape<MainValue> {
-        let mut data = vec![TapeConstructor::Head(TapeValue::Empty)];
-        for char in s.chars() {
-            match char {
-                'a' => data.push(TapeConstructor::Value(TapeValue::Value(MainValue::A))),
-                'b' => data.push(TapeConstructor::Value(TapeValue::Value(MainValue::B))),
-                '#' => data.push(TapeConstructor::Value(TapeValue::Value(MainValue::Hash))),
-                _ => panic!("Invalid character in M3 main tape"),
-            }
-        }
-        Tape::new(data, None)
+    use crate::{
+        chooser::{with_chooser, ScriptedChooser},
+        combinators::{And, LoopWhile, MainAtSeparator},
+        m1::M1,
+        m2::M2,
+        machine::{certificate_tape, TuringMachine, Verifier},
+    };
+
+    /// Tapes for the words of `s` separated by hashes
+    fn input(s: &str) -> (Tape<MainValue>, Tape<AuxValue>) {
+        M3::input_for(&s.split('#').collect::<Vec<_>>(), None)
     }
 
     fn is_present(s: &str, substring: &str) -> bool {
@@ -203,7 +60,7 @@ mod tests {
                 current_substring_char.next();
             }
         }
-        return current_substring_char.peek().is_none();
+        current_substring_char.peek().is_none()
     }
 
     fn m3_own(s: &str, substring: &str) -> bool {
@@ -222,14 +79,137 @@ mod tests {
     #[test]
     fn run() {
         let s = "aba#aba#aba";
-        let main_tape = str_to_m3_main_tape(s);
-        let aux_tape = Tape::new(vec![TapeConstructor::Head(TapeValue::Empty)], None);
+        let (main_tape, aux_tape) = input(s);
         let mut m3 = M3::new(main_tape, aux_tape);
         let output = m3.run();
-        let substring = m3.substring();
+        let substring = m3.substring().unwrap();
         assert_eq!(m3_own(s, substring), output);
     }
 
+    #[test]
+    fn run_with_certificate() {
+        let s = "aba#abba#aabba";
+        let mut certificates = vec![vec![]];
+        for _ in 0..4 {
+            certificates = certificates
+                .into_iter()
+                .flat_map(|certificate: Vec<AuxValue>| {
+                    [AuxValue::A, AuxValue::B].into_iter().map(move |value| {
+                        let mut certificate = certificate.clone();
+                        certificate.push(value);
+                        certificate
+                    })
+                })
+                .collect();
+            for certificate in &certificates {
+                let substring: String = certificate
+                    .iter()
+                    .map(|value| match value {
+                        AuxValue::A => 'a',
+                        AuxValue::B => 'b',
+                    })
+                    .collect();
+                let (main_tape, aux_tape) = input(s);
+                assert_eq!(
+                    M3::accepts_certificate(
+                        main_tape,
+                        aux_tape,
+                        certificate_tape(certificate, None)
+                    ),
+                    m3_own(s, &substring),
+                    "{}",
+                    substring
+                );
+            }
+        }
+
+        let (main_tape, aux_tape) = input(s);
+        let mut m3 = M3::with_certificate(
+            main_tape,
+            aux_tape,
+            certificate_tape(&[AuxValue::A, AuxValue::B, AuxValue::A], None),
+        );
+        assert!(m3.run());
+        assert_eq!(m3.substring(), Some("aba"));
+    }
+
+    /// Output and steps of `T` on the words of `s`, making the choices of `script`
+    fn scripted<T: TuringMachine<Main = MainValue, Aux = AuxValue>>(
+        s: &str,
+        script: &[usize],
+    ) -> (bool, u64) {
+        let steps_ran = Rc::new(RefCell::new(0));
+        let (main_tape, aux_tape) = M3::input_for(
+            &s.split('#').collect::<Vec<_>>(),
+            Some(Rc::clone(&steps_ran)),
+        );
+        let output = with_chooser(
+            Rc::new(RefCell::new(ScriptedChooser::new(script.to_vec()))),
+            || T::new(main_tape, aux_tape).run(),
+        );
+        let steps_ran = *steps_ran.borrow();
+        (output, steps_ran)
+    }
+
+    #[test]
+    fn composed() {
+        // M3 is M2 then M1 on each later word, with only the steps of checking its input and
+        // reading back its two letter guess of its own
+        type Composed = And<M2, LoopWhile<M1, MainAtSeparator>>;
+
+        for (s, script) in [
+            ("ab#ab#ab", [0, 1]),
+            ("ab#ba#bab", [1, 0]),
+            ("ab#ba#bab", [1, 1]),
+        ] {
+            let (output, steps) = scripted::<Composed>(s, &script);
+            let check = 2 * (s.len() as u64 + 1);
+            assert_eq!(
+                scripted::<M3>(s, &script),
+                (output, check + steps + 6),
+                "{}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn steps() {
+        // Checking the input, M2 guessing ab, a move and a read for each letter of the guess and
+        // the empty cell after it to read it back, then M1 on each later word and a read of the
+        // hash or empty cell after it. M2 and M1 each check their input too.
+        let steps_ran = Rc::new(RefCell::new(0));
+        let (mut main_tape, mut aux_tape) = M2::input_for("ab", Some(Rc::clone(&steps_ran)));
+        assert!(with_chooser(
+            Rc::new(RefCell::new(ScriptedChooser::new(vec![0, 1]))),
+            || M2::run_on(&mut main_tape, &mut aux_tape)
+        ));
+        let m2_steps = steps_ran.replace(0);
+        let (mut main_tape, mut aux_tape) = M1::input_for("ab", "ab", Some(Rc::clone(&steps_ran)));
+        assert!(M1::run_on(&mut main_tape, &mut aux_tape));
+        let m1_steps = steps_ran.replace(0);
+
+        let steps = 2 * 9 + m2_steps + 2 * 3 + 2 * (m1_steps + 1);
+        assert_eq!(scripted::<M3>("ab#ab#ab", &[0, 1]), (true, steps));
+        assert_eq!(steps, 108);
+    }
+
+    #[test]
+    fn call_stack() {
+        // Each check runs in the frame of the loop over the later words, and M3 runs every step
+        // but those of checking its input
+        assert_eq!(scripted::<M3>("ab#ab#ab", &[0, 1]), (true, 108));
+        let summary = crate::call_stack::summary();
+        assert_eq!(
+            summary
+                .iter()
+                .map(|(name, summary)| (*name, summary.invocations))
+                .collect::<Vec<_>>(),
+            [("LoopWhile", 1), ("M1", 2), ("M2", 1), ("M3", 1)]
+        );
+        assert_eq!(summary["M3"].steps, 108 - 2 * 9);
+    }
+
     #[test]
     fn test_is_present() {
         assert!(is_present("abba", "aa"));
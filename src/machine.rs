@@ -1,49 +1,409 @@
-use log::trace;
-use std::{cell::RefCell, rc::Rc};
+use std::{
+    cell::RefCell,
+    fmt::{self, Display},
+    mem,
+    rc::Rc,
+};
 
-use crate::tape::{Tape, TapeConstructor, TapeValue};
+use crate::{
+    alphabet::{Alphabet, MachineNames, WordAlphabet},
+    chooser,
+    tape::{Tape, TapeConstructor, TapeValue},
+};
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum MainValue {
     A,
     B,
     Hash,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+impl Alphabet for MainValue {
+    fn symbols() -> &'static [Self] {
+        &[Self::A, Self::B, Self::Hash]
+    }
+
+    fn to_char(&self) -> char {
+        match self {
+            Self::A => 'a',
+            Self::B => 'b',
+            Self::Hash => '#',
+        }
+    }
+
+    fn is_separator(&self) -> bool {
+        matches!(self, Self::Hash)
+    }
+}
+
+impl WordAlphabet for MainValue {
+    type Letter = AuxValue;
+
+    const MACHINE_NAMES: MachineNames = MachineNames {
+        subsequence: "M1",
+        guesser: "M2",
+        common_subsequence: "M3",
+    };
+}
+
+impl Display for MainValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum AuxValue {
     A,
     B,
 }
 
-pub struct DestroyOutput {
-    main_tape: Tape<MainValue>,
-    aux_tape: Tape<AuxValue>,
+impl Alphabet for AuxValue {
+    fn symbols() -> &'static [Self] {
+        &[Self::A, Self::B]
+    }
+
+    fn to_char(&self) -> char {
+        match self {
+            Self::A => 'a',
+            Self::B => 'b',
+        }
+    }
+}
+
+impl Display for AuxValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
 }
 
-impl DestroyOutput {
-    pub fn new(main_tape: Tape<MainValue>, aux_tape: Tape<AuxValue>) -> Self {
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
+pub struct DestroyOutput<M: Alphabet, A: Alphabet> {
+    main_tape: Tape<M>,
+    aux_tape: Tape<A>,
+}
+
+impl<M: Alphabet, A: Alphabet> DestroyOutput<M, A> {
+    pub const fn new(main_tape: Tape<M>, aux_tape: Tape<A>) -> Self {
         Self {
             main_tape,
             aux_tape,
         }
     }
 
-    pub fn main_tape(&self) -> &Tape<MainValue> {
+    pub const fn main_tape(&self) -> &Tape<M> {
         &self.main_tape
     }
 
-    pub fn aux_tape(&self) -> &Tape<AuxValue> {
+    pub const fn aux_tape(&self) -> &Tape<A> {
         &self.aux_tape
     }
+
+    pub fn into_tapes(self) -> (Tape<M>, Tape<A>) {
+        (self.main_tape, self.aux_tape)
+    }
+}
+
+/// How a run ended
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Outcome {
+    Accept,
+    Reject,
+    /// A head moved left off the first cell of a tape that crashes there, see `LeftEnd::Crash`
+    Crash,
+    /// A head tried to move past an end marker, see `Tape::with_end_markers`
+    BoundViolation,
+    /// A deterministic machine revisited a configuration `cycle` transitions after first reaching
+    /// it, so would never halt
+    Looping {
+        cycle: u64,
+    },
+}
+
+impl Outcome {
+    /// Outcome of a run that halted because of `tape`, if any
+    pub const fn of_tape<T: Clone>(tape: &Tape<T>) -> Option<Self> {
+        if tape.has_violated_bound() {
+            Some(Self::BoundViolation)
+        } else if tape.has_crashed() {
+            Some(Self::Crash)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<bool> for Outcome {
+    fn from(accepted: bool) -> Self {
+        if accepted {
+            Self::Accept
+        } else {
+            Self::Reject
+        }
+    }
 }
 
 pub trait TuringMachine {
-    fn new(main_tape: Tape<MainValue>, aux_tape: Tape<AuxValue>) -> Self;
+    type Main: Alphabet;
+    type Aux: Alphabet;
+
+    fn new(main_tape: Tape<Self::Main>, aux_tape: Tape<Self::Aux>) -> Self;
+    /// Whether the machine accepts. `#[must_use]` sits here rather than on each machine's `run`,
+    /// where it would have no effect.
+    #[must_use]
     fn run(&mut self) -> bool;
-    fn destroy(self) -> DestroyOutput;
+    fn destroy(self) -> DestroyOutput<Self::Main, Self::Aux>;
+
+    /// Runs the machine on borrowed tapes, leaving them as the machine left them
+    fn run_on(main_tape: &mut Tape<Self::Main>, aux_tape: &mut Tape<Self::Aux>) -> bool
+    where
+        Self: Sized,
+    {
+        run_on_with(main_tape, aux_tape, Self::new)
+    }
+
+    /// Runs the machine on borrowed tapes like `run_on`. A machine written as code must stop once
+    /// either tape has halted, see `Tape::has_halted`, and its outcome is then a crash or bound
+    /// violation whatever it returned.
+    #[cfg(test)]
+    fn outcome_on(main_tape: &mut Tape<Self::Main>, aux_tape: &mut Tape<Self::Aux>) -> Outcome
+    where
+        Self: Sized,
+    {
+        let accepted = Self::run_on(main_tape, aux_tape);
+        Outcome::of_tape(main_tape)
+            .or_else(|| Outcome::of_tape(aux_tape))
+            .unwrap_or_else(|| accepted.into())
+    }
+}
+
+/// Runs the machine built by `new` on borrowed tapes, moving them in and out of the machine
+/// rather than copying them
+pub fn run_on_with<M: TuringMachine>(
+    main_tape: &mut Tape<M::Main>,
+    aux_tape: &mut Tape<M::Aux>,
+    new: impl FnOnce(Tape<M::Main>, Tape<M::Aux>) -> M,
+) -> bool {
+    let mut machine = new(mem::take(main_tape), mem::take(aux_tape));
+    let output = machine.run();
+    (*main_tape, *aux_tape) = machine.destroy().into_tapes();
+    output
+}
+
+/// Deterministic machine that reads a certificate over its aux alphabet from a read-only tape in
+/// place of its nondeterministic choices
+pub trait Verifier: TuringMachine {
+    fn with_certificate(
+        main_tape: Tape<Self::Main>,
+        aux_tape: Tape<Self::Aux>,
+        certificate_tape: Tape<Self::Aux>,
+    ) -> Self;
+
+    #[cfg(test)]
+    fn accepts_certificate(
+        main_tape: Tape<Self::Main>,
+        aux_tape: Tape<Self::Aux>,
+        certificate_tape: Tape<Self::Aux>,
+    ) -> bool
+    where
+        Self: Sized,
+    {
+        Self::with_certificate(main_tape, aux_tape, certificate_tape).run()
+    }
+}
+
+/// Nondeterministic choice among `count` options, made by the chooser set with
+/// `chooser::with_chooser`
+pub fn choose_among(count: usize) -> usize {
+    chooser::choose_among(count)
 }
 
-pub fn choose() -> bool {
-    rand::random()
+/// Certificate tape in the layout read by `M2::with_certificate`: an empty head followed by the
+/// certificate and an empty cell
+pub fn certificate_tape<T: Alphabet>(
+    certificate: &[T],
+    steps_ran: Option<Rc<RefCell<u64>>>,
+) -> Tape<T> {
+    let mut data = vec![TapeConstructor::Head(TapeValue::Empty)];
+    for value in certificate {
+        data.push(TapeConstructor::Value(TapeValue::Value(value.clone())));
+    }
+    data.push(TapeConstructor::Value(TapeValue::Empty));
+    Tape::new(data, steps_ran)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::TapeBuilder,
+        chooser::{with_chooser, ScriptedChooser},
+        m1::M1,
+        m2::M2,
+        m3::M3,
+        test_support::Bit,
+    };
+
+    /// Flips every bit on the main tape right of the head, over any aux alphabet
+    struct Invert<A: Alphabet> {
+        main_tape: Tape<Bit>,
+        aux_tape: Tape<A>,
+    }
+
+    impl<A: Alphabet> TuringMachine for Invert<A> {
+        type Main = Bit;
+        type Aux = A;
+
+        fn new(main_tape: Tape<Bit>, aux_tape: Tape<A>) -> Self {
+            Self {
+                main_tape,
+                aux_tape,
+            }
+        }
+
+        fn run(&mut self) -> bool {
+            loop {
+                self.main_tape.right();
+                match self.main_tape.read() {
+                    TapeValue::Value(Bit::Zero) => self.main_tape.write(TapeValue::Value(Bit::One)),
+                    TapeValue::Value(Bit::One) => self.main_tape.write(TapeValue::Value(Bit::Zero)),
+                    TapeValue::Empty => return true,
+                }
+            }
+        }
+
+        fn destroy(self) -> DestroyOutput<Bit, A> {
+            DestroyOutput::new(self.main_tape, self.aux_tape)
+        }
+    }
+
+    #[test]
+    fn generic_alphabets() {
+        let mut main_tape = Tape::new(
+            "_0110_"
+                .chars()
+                .enumerate()
+                .map(|(index, char)| {
+                    let value = TapeValue::from_char(char).unwrap();
+                    if index == 0 {
+                        TapeConstructor::Head(value)
+                    } else {
+                        TapeConstructor::Value(value)
+                    }
+                })
+                .collect(),
+            None,
+        );
+        let mut aux_tape = Tape::<MainValue>::default();
+        assert!(Invert::run_on(&mut main_tape, &mut aux_tape));
+        assert_eq!(
+            main_tape
+                .data()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<String>(),
+            "_1001_"
+        );
+    }
+
+    #[test]
+    fn borrowed_tapes() {
+        // Borrowed tapes end up as a machine run on owned tapes leaves them
+        let steps_ran = Rc::new(RefCell::new(0));
+        let (main_tape, aux_tape) = M1::input_for("abba", "ab", Some(Rc::clone(&steps_ran)));
+        let mut m1 = M1::new(main_tape.clone(), aux_tape.clone());
+        assert!(m1.run());
+        let destroy = m1.destroy();
+        let owned_steps = steps_ran.replace(0);
+
+        let (mut main_tape, mut aux_tape) = (main_tape, aux_tape);
+        assert!(M1::run_on(&mut main_tape, &mut aux_tape));
+        assert_eq!(&main_tape, destroy.main_tape());
+        assert_eq!(&aux_tape, destroy.aux_tape());
+        assert_eq!(main_tape.head(), 5);
+        assert_eq!(aux_tape.head(), 0);
+
+        // Both count the steps of checking the input as well as the run
+        assert_eq!(*steps_ran.borrow(), owned_steps);
+        assert_eq!(owned_steps, 17 + 22);
+    }
+
+    #[test]
+    fn certificate_run_on_with() {
+        let certificate = certificate_tape(&[AuxValue::B, AuxValue::A], None);
+        let (mut main_tape, mut aux_tape) = M2::input_for("ab", None);
+        assert!(super::run_on_with(
+            &mut main_tape,
+            &mut aux_tape,
+            |main_tape, aux_tape| { M2::with_certificate(main_tape, aux_tape, certificate) }
+        ));
+        assert_eq!(main_tape.head(), 3);
+        assert_eq!(
+            aux_tape
+                .data()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<String>(),
+            "_ba"
+        );
+    }
+
+    #[test]
+    fn end_markers() {
+        // Machines written as code halt at an end marker rather than carrying on from the cell
+        // before it
+        let main_tape = || TapeBuilder::new().head().separator().word("ab");
+        let aux_tape = || TapeBuilder::new().head().blank().word("a").blank();
+        for (main_tape, outcome) in [
+            (main_tape().build(), Outcome::BoundViolation),
+            (main_tape().separator().build(), Outcome::Accept),
+        ] {
+            assert_eq!(
+                M1::outcome_on(
+                    &mut main_tape.with_end_markers(),
+                    &mut aux_tape().build().with_end_markers()
+                ),
+                outcome
+            );
+        }
+
+        // M2 has no room on a single cell aux tape to write its guess
+        let (main_tape, aux_tape) = M2::input_for("ab", None);
+        assert_eq!(
+            M2::outcome_on(
+                &mut main_tape.with_end_markers(),
+                &mut aux_tape.with_end_markers()
+            ),
+            Outcome::BoundViolation
+        );
+
+        // M3 needs a cell after the guess and after the last word
+        let main_tape = || TapeBuilder::new().head().blank().words(&["ab", "ab"]);
+        for (main_tape, blanks, outcome) in [
+            (main_tape().blank().build(), 3, Outcome::Accept),
+            (main_tape().blank().build(), 2, Outcome::BoundViolation),
+            (main_tape().build(), 3, Outcome::BoundViolation),
+        ] {
+            let aux_tape = TapeBuilder::new().head().blank().blanks(blanks).build();
+            let scripted = with_chooser(
+                Rc::new(RefCell::new(ScriptedChooser::new(vec![0, 1]))),
+                || {
+                    M3::outcome_on(
+                        &mut main_tape.with_end_markers(),
+                        &mut aux_tape.with_end_markers(),
+                    )
+                },
+            );
+            assert_eq!(scripted, outcome);
+        }
+    }
 }
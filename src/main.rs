@@ -3,7 +3,7 @@
 #![warn(clippy::cast_possible_wrap)]
 #![warn(clippy::default_trait_access)]
 #![warn(clippy::else_if_without_else)]
-#![warn(clippy::empty_enum)]
+#![warn(clippy::empty_enums)]
 #![warn(clippy::empty_line_after_outer_attr)]
 #![warn(clippy::enum_glob_use)]
 #![warn(clippy::equatable_if_let)]
@@ -22,7 +22,6 @@
 #![warn(clippy::map_err_ignore)]
 #![warn(clippy::map_unwrap_or)]
 #![warn(clippy::match_bool)]
-#![warn(clippy::match_on_vec_items)]
 #![warn(clippy::match_same_arms)]
 #![warn(clippy::match_wild_err_arm)]
 #![warn(clippy::match_wildcard_for_single_variants)]
@@ -37,7 +36,6 @@
 #![warn(clippy::redundant_pub_crate)]
 #![warn(clippy::single_match_else)]
 #![warn(clippy::str_to_string)]
-#![warn(clippy::string_to_string)]
 #![warn(clippy::trait_duplication_in_bounds)]
 #![warn(clippy::unused_async)]
 #![warn(clippy::unused_self)]
@@ -46,37 +44,238 @@
 #![warn(clippy::wildcard_imports)]
 #![warn(clippy::zero_sized_map_values)]
 
+mod alphabet;
+mod bench;
+mod builder;
+mod call_stack;
+mod chooser;
+mod combinators;
+mod common_subsequence;
+mod cost;
+mod crossing;
+mod determinize;
+mod generate;
+mod grid;
+mod guess_and_verify;
+mod guesser;
+#[cfg(feature = "serde")]
+mod json;
 mod m1;
 mod m2;
 mod m3;
 mod machine;
+mod multi_tape;
+mod pattern;
+mod single_tape;
+mod space;
+mod subsequence;
+mod table;
 mod tape;
+#[cfg(test)]
+mod test_support;
+mod tracks;
+mod words;
 
-use machine::{AuxValue, MainValue, TuringMachine};
-use tape::{TapeConstructor, TapeValue};
+use alphabet::Alphabet;
+use chooser::{Chooser, RandomChooser, ScriptedChooser, SeededChooser};
+use guess_and_verify::{GuessAndVerify, GuessLength, Mode};
+use m3::M3;
+use machine::{certificate_tape, AuxValue, TuringMachine, Verifier};
+use multi_tape::StepCounting;
 
-use clap::{Parser, Subcommand};
+use clap::{ArgEnum, Parser, Subcommand};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{cell::RefCell, rc::Rc};
 
-// #[derive(Parser)]
-// #[clap(version, author, about, long_about = None)]
-// struct Cli {
-//     #[clap(subcommand)]
-//     command: Commands,
-// }
+#[derive(Parser)]
+#[clap(version, author, about, long_about = None)]
+struct Cli {
+    #[clap(subcommand)]
+    command: Commands,
+}
 
-// #[derive(Subcommand)]
-// enum Commands {
-//     /// Run with specific values of n and m
-//     Run {
-//         /// n
-//         #[clap(short)]
-//         n: u64,
-
-//         /// m
-//         #[clap(short)]
-//         m: u64,
-//     },
-// }
+#[derive(Subcommand)]
+enum Commands {
+    /// Run M3 on words separated by hashes, e.g. `aba#abba#bab`
+    Run {
+        input: String,
+
+        /// Cost model to count steps under
+        #[clap(short, long, arg_enum, default_value = "ops")]
+        cost: Cost,
+
+        /// Make M3's choices from these comma separated option indices instead of at random
+        #[clap(long, use_value_delimiter = true)]
+        choices: Option<Vec<usize>>,
+
+        /// Run every branch of M3's choices until one accepts, then report that branch
+        #[clap(short, long, conflicts_with = "choices")]
+        every_branch: bool,
+
+        /// Check this word of a's and b's as M3's guess instead of guessing, e.g. `ab`
+        #[clap(long, conflicts_with_all = &["choices", "every-branch"])]
+        certificate: Option<String>,
+
+        /// Check every guess in turn until M3 accepts one, then report it
+        #[clap(short, long, conflicts_with_all = &["choices", "every-branch", "certificate"])]
+        search: bool,
+    },
+    /// Time running M1 over every word on cloned tapes against borrowed tapes
+    Bench {
+        /// Largest number of words, doubling from 1
+        #[clap(short, default_value_t = 8192)]
+        words: usize,
+
+        /// Length of each word
+        #[clap(short, default_value_t = 16)]
+        length: usize,
+
+        /// Runs to average over
+        #[clap(short, default_value_t = 10)]
+        runs: u32,
+    },
+    /// Measure steps and space taken by M3 over inputs of growing numbers and lengths of words
+    Grid {
+        /// Largest number of words, doubling from 2
+        #[clap(short, default_value_t = 8)]
+        words: usize,
+
+        /// Largest word length, doubling from 1
+        #[clap(short, default_value_t = 16)]
+        length: usize,
+
+        /// Measure on random words drawn with this seed instead of alternating a's and b's, and
+        /// make M3's choices with it
+        #[clap(short, long)]
+        seed: Option<u64>,
+
+        /// Draw random inputs M3 accepts or rejects, deciding each from its words
+        #[clap(short, long, arg_enum, requires = "seed")]
+        bias: Option<Bias>,
+    },
+    /// Print crossing sequences of M1's main tape on each main word against an aux word
+    Crossings { aux: String, mains: Vec<String> },
+    /// Compare steps taken by M1 as a two-tape table machine and simulated on a single tape
+    Simulate {
+        /// Largest main word length, doubling from 2
+        #[clap(short, default_value_t = 256)]
+        length: usize,
+
+        /// Count one step per transition rather than per read, write and move
+        #[clap(short = 't', long)]
+        per_transition: bool,
+    },
+    /// Compare steps taken by one branch of M2 as a table machine and by its determinized search
+    Determinize {
+        /// Largest word length, growing by 1 from 1
+        #[clap(short, default_value_t = 7)]
+        length: usize,
+    },
+}
+
+/// Built in cost models, see `cost::CostModel`
+#[derive(Clone, Copy, ArgEnum)]
+enum Cost {
+    Moves,
+    Ops,
+    Transitions,
+}
+
+impl Cost {
+    fn model(self) -> Rc<dyn cost::CostModel> {
+        match self {
+            Self::Moves => Rc::new(cost::MovesOnly),
+            Self::Ops => Rc::new(cost::PrimitiveOps),
+            Self::Transitions => Rc::new(cost::Transitions),
+        }
+    }
+}
+
+/// Answer random grid inputs are biased towards, see `generate::Generator::with_bias`
+#[derive(Clone, Copy, ArgEnum)]
+enum Bias {
+    Accept,
+    Reject,
+}
+
+/// Choices of the first branch of M3's choices on `input` that accepts, running every branch until
+/// one does
+fn accepting_choices(input: &str) -> Option<Vec<usize>> {
+    let words: Vec<_> = input.split('#').collect();
+    let exploration = chooser::explore(true, || {
+        let (main_tape, aux_tape) = M3::input_for(&words, None);
+        M3::new(main_tape, aux_tape).run()
+    });
+    println!("Branches: {}", exploration.branches);
+    exploration.accepted
+}
+
+/// Runs M3 on `input`, reading its guess from `certificate` if given
+fn run(input: &str, certificate: Option<&[AuxValue]>) {
+    let steps_ran = Rc::new(RefCell::new(0));
+    let words: Vec<_> = input.split('#').collect();
+    let (main_tape, aux_tape) = M3::input_for(&words, Some(Rc::clone(&steps_ran)));
+
+    let mut machine = match certificate {
+        Some(certificate) => M3::with_certificate(
+            main_tape,
+            aux_tape,
+            certificate_tape(certificate, Some(Rc::clone(&steps_ran))),
+        ),
+        None => M3::new(main_tape, aux_tape),
+    };
+    println!(
+        "Result: {}\nSubstring: {}\nSteps ({}): {}",
+        machine.run(),
+        machine.substring().unwrap_or("none"),
+        cost::name(),
+        steps_ran.borrow(),
+    );
+    let destroy = machine.destroy();
+    println!(
+        "Main tape: {}\nAux tape: {}",
+        destroy.main_tape().space(),
+        destroy.aux_tape().space()
+    );
+    print_call_stack();
+}
+
+/// Runs M3 on `input` with every guess as long as its first word in turn, until it accepts one
+fn search(input: &str) {
+    let steps_ran = Rc::new(RefCell::new(0));
+    let words: Vec<_> = input.split('#').collect();
+    let (main_tape, aux_tape) = M3::input_for(&words, Some(Rc::clone(&steps_ran)));
+
+    let mut machine = GuessAndVerify::<M3>::with_mode(
+        main_tape,
+        aux_tape,
+        GuessLength::FirstWord,
+        Mode::Exhaustive,
+    );
+    let output = machine.run();
+    let certificate = machine.certificate().map_or_else(
+        || "none".to_owned(),
+        |certificate| certificate.iter().map(Alphabet::to_char).collect(),
+    );
+    println!(
+        "Result: {}\nCertificate: {}\nSteps ({}): {}",
+        output,
+        certificate,
+        cost::name(),
+        steps_ran.borrow(),
+    );
+    print_call_stack();
+}
+
+/// Steps and invocations of each machine in the last run
+fn print_call_stack() {
+    for (name, summary) in call_stack::summary() {
+        println!(
+            "{}: {} steps over {} invocations",
+            name, summary.steps, summary.invocations
+        );
+    }
+}
 
 // fn n_m_steps(n: u64, m: u64) -> u64 {
 //     if n <= m {
@@ -96,7 +295,8 @@ use clap::{Parser, Subcommand};
 //     fn main() {
 //         for n in 1..101 {
 //             for m in 1..101 {
-//                 let mut main_tape = vec![TapeConstructor::Head(TapeValue::Value(MainValue::Hash))];
+//                 let mut main_tape =
+//                     vec![TapeConstructor::Head(TapeValue::Value(MainValue::Hash))];
 //                 for _ in 0..n {
 //                     main_tape.push(TapeConstructor::Value(TapeValue::Value(MainValue::A)));
 //                 }
@@ -146,29 +346,81 @@ fn main() {
 
     env_logger::init();
 
-    // let cli = Cli::parse();
-
-    // match cli.command {
-    //     Commands::Run { n, m } => {
-    //         let mut main_tape = vec![TapeConstructor::Head(TapeValue::Value(MainValue::Hash))];
-    //         for _ in 0..n {
-    //             main_tape.push(TapeConstructor::Value(TapeValue::Value(MainValue::A)));
-    //         }
-    //         main_tape.push(TapeConstructor::Value(TapeValue::Value(MainValue::Hash)));
-
-    //         let mut aux_tape = vec![TapeConstructor::Head(TapeValue::Empty)];
-    //         for _ in 0..m {
-    //             aux_tape.push(TapeConstructor::Value(TapeValue::Value(AuxValue::A)));
-    //         }
-    //         aux_tape.push(TapeConstructor::Value(TapeValue::Empty));
-
-    //         let mut machine = TuringMachine::new(main_tape, aux_tape);
-    //         println!(
-    //             "Result: {}\nn={n}\nm={m}\nSteps: {}\nn_m_steps: {}",
-    //             machine.run(),
-    //             machine.steps_ran(),
-    //             n_m_steps(n, m),
-    //         );
-    //     }
-    // }
+    let cli = Cli::parse();
+
+    match cli.command {
+        Commands::Run {
+            input,
+            cost,
+            search: true,
+            ..
+        } => cost::with_cost_model(cost.model(), || search(&input)),
+        Commands::Run {
+            input,
+            cost,
+            choices,
+            every_branch,
+            certificate,
+            search: false,
+        } => {
+            let certificate: Option<Vec<_>> = certificate.map(|certificate| {
+                certificate
+                    .chars()
+                    .map(|char| AuxValue::from_char(char).expect("Certificate must be a's and b's"))
+                    .collect()
+            });
+            let choices = if every_branch {
+                if let Some(choices) = accepting_choices(&input) {
+                    Some(choices)
+                } else {
+                    println!("Result: false");
+                    return;
+                }
+            } else {
+                choices
+            };
+            let chooser: Rc<RefCell<dyn Chooser>> = match choices {
+                Some(choices) => Rc::new(RefCell::new(ScriptedChooser::new(choices))),
+                None => Rc::new(RefCell::new(RandomChooser)),
+            };
+            chooser::with_chooser(chooser, || {
+                cost::with_cost_model(cost.model(), || run(&input, certificate.as_deref()));
+            });
+        }
+        Commands::Bench {
+            words,
+            length,
+            runs,
+        } => bench::bench(words, length, runs),
+        Commands::Grid {
+            words,
+            length,
+            seed,
+            bias,
+        } => {
+            let mut inputs = seed.map_or(grid::Inputs::Alternating, |seed| grid::Inputs::Random {
+                rng: Box::new(StdRng::seed_from_u64(seed)),
+                bias: bias.map(|bias| matches!(bias, Bias::Accept)),
+            });
+            // Seeded runs make the same choices each time, as well as drawing the same inputs
+            let chooser: Rc<RefCell<dyn Chooser>> = match seed {
+                Some(seed) => Rc::new(RefCell::new(SeededChooser::new(seed))),
+                None => Rc::new(RefCell::new(RandomChooser)),
+            };
+            chooser::with_chooser(chooser, || grid::grid(words, length, &mut inputs));
+        }
+        Commands::Crossings { aux, mains } => crossing::report(&aux, &mains),
+        Commands::Simulate {
+            length,
+            per_transition,
+        } => single_tape::report(
+            length,
+            if per_transition {
+                StepCounting::PerTransition
+            } else {
+                StepCounting::PerOperation
+            },
+        ),
+        Commands::Determinize { length } => determinize::report(length),
+    }
 }
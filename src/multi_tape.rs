@@ -0,0 +1,430 @@
+use std::{cell::RefCell, mem, rc::Rc};
+
+use crate::{
+    alphabet::Alphabet,
+    cost::{self, CostModel, PrimitiveOps, Transitions},
+    machine::TuringMachine,
+    tape::{Move, Tape, TapeValue},
+    tracks::Tracks,
+};
+
+/// How a table counts its transitions whatever the current cost model, see
+/// `Table::with_step_counting`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StepCounting {
+    /// One step per transition, however many heads it reads, writes and moves
+    PerTransition,
+    /// One step per read, write and move on each tape, as tapes count them on their own
+    PerOperation,
+}
+
+impl StepCounting {
+    /// Cost model that counts this way
+    pub fn cost_model(self) -> Rc<dyn CostModel> {
+        match self {
+            Self::PerTransition => Rc::new(Transitions),
+            Self::PerOperation => Rc::new(PrimitiveOps),
+        }
+    }
+}
+
+/// Tapes of a multi-tape machine, each over its own alphabet or tracks for tuples of up to 4 tapes,
+/// or of borrowed tapes, and any number over one alphabet for a `Vec` of tapes
+pub trait TapeSet {
+    /// Symbol under each head, in tape order
+    type Symbols;
+    /// Symbol to write under each head, `None` to leave the cell as it is
+    type Writes;
+
+    #[allow(dead_code)]
+    fn count(&self) -> usize;
+    fn read_all(&self) -> Self::Symbols;
+    fn write_all(&mut self, writes: Self::Writes);
+    /// Moves every head, `moves` has one entry per tape
+    fn move_heads(&mut self, moves: &[Move]);
+    /// Step counter of each tape, in tape order
+    fn counters(&self) -> Vec<Option<Rc<RefCell<u64>>>>;
+
+    /// One synchronous transition: reads every head, then writes and moves each as `delta` says,
+    /// costing what the current cost model charges for a transition. `delta` returns `None` to
+    /// halt instead.
+    fn transition<R>(
+        &mut self,
+        delta: impl FnOnce(Self::Symbols) -> Option<(Self::Writes, Vec<Move>, R)>,
+    ) -> Option<R> {
+        let counters = self.counters();
+        let before = counters
+            .iter()
+            .map(|counter| counter.as_ref().map_or(0, |counter| *counter.borrow()))
+            .collect::<Vec<_>>();
+
+        let (writes, moves, output) = if let Some(transition) = delta(self.read_all()) {
+            transition
+        } else {
+            count_transition(&counters, &before, cost::halt);
+            return None;
+        };
+        self.write_all(writes);
+        self.move_heads(&moves);
+
+        count_transition(&counters, &before, cost::transition);
+        Some(output)
+    }
+}
+
+/// Replaces the steps each distinct counter counted for the operations of a transition with
+/// `cost` of them
+fn count_transition(
+    counters: &[Option<Rc<RefCell<u64>>>],
+    before: &[u64],
+    cost: impl Fn(u64) -> u64,
+) {
+    for (index, (counter, before)) in counters.iter().zip(before).enumerate() {
+        if let Some(counter) = counter {
+            let first = counters[..index]
+                .iter()
+                .flatten()
+                .all(|other| !Rc::ptr_eq(other, counter));
+            if first {
+                let operations = *counter.borrow() - before;
+                *counter.borrow_mut() = before + cost(operations);
+            }
+        }
+    }
+}
+
+macro_rules! tape_set {
+    ($count:literal; $($alphabet:ident $index:tt),+) => {
+        tape_set!(@impl $count; ($(Tape<$alphabet>,)+); $($alphabet $index),+);
+        tape_set!(@impl $count; ($(&mut Tape<$alphabet>,)+); $($alphabet $index),+);
+    };
+    (@impl $count:literal; $tapes:ty; $($alphabet:ident $index:tt),+) => {
+        impl<$($alphabet: Tracks),+> TapeSet for $tapes {
+            type Symbols = ($(TapeValue<$alphabet>,)+);
+            type Writes = ($(Option<TapeValue<$alphabet>>,)+);
+
+            fn count(&self) -> usize {
+                $count
+            }
+
+            fn read_all(&self) -> Self::Symbols {
+                ($(self.$index.read(),)+)
+            }
+
+            fn write_all(&mut self, writes: Self::Writes) {
+                $(
+                    if let Some(value) = writes.$index {
+                        self.$index.write(value);
+                    }
+                )+
+            }
+
+            fn move_heads(&mut self, moves: &[Move]) {
+                assert_eq!(moves.len(), $count, "Must move every head");
+                $(self.$index.move_head(moves[$index]);)+
+            }
+
+            fn counters(&self) -> Vec<Option<Rc<RefCell<u64>>>> {
+                vec![$(self.$index.steps_ran_counter()),+]
+            }
+        }
+    };
+}
+
+tape_set!(1; A 0);
+tape_set!(2; A 0, B 1);
+tape_set!(3; A 0, B 1, C 2);
+tape_set!(4; A 0, B 1, C 2, D 3);
+
+impl<T: Alphabet> TapeSet for Vec<Tape<T>> {
+    type Symbols = Vec<TapeValue<T>>;
+    type Writes = Vec<Option<TapeValue<T>>>;
+
+    fn count(&self) -> usize {
+        self.len()
+    }
+
+    fn read_all(&self) -> Self::Symbols {
+        self.iter().map(Tape::read).collect()
+    }
+
+    fn write_all(&mut self, writes: Self::Writes) {
+        assert_eq!(writes.len(), self.len(), "Must write under every head");
+        for (tape, value) in self.iter_mut().zip(writes) {
+            if let Some(value) = value {
+                tape.write(value);
+            }
+        }
+    }
+
+    fn move_heads(&mut self, moves: &[Move]) {
+        assert_eq!(moves.len(), self.len(), "Must move every head");
+        for (tape, direction) in self.iter_mut().zip(moves) {
+            tape.move_head(*direction);
+        }
+    }
+
+    fn counters(&self) -> Vec<Option<Rc<RefCell<u64>>>> {
+        self.iter().map(Tape::steps_ran_counter).collect()
+    }
+}
+
+/// Machine over any number of tapes, declared by its `TapeSet`
+#[allow(dead_code)]
+pub trait MultiTapeMachine {
+    type Tapes: TapeSet + Default;
+
+    fn from_tapes(tapes: Self::Tapes) -> Self;
+    fn run(&mut self) -> bool;
+    fn into_tapes(self) -> Self::Tapes;
+
+    /// Runs the machine on borrowed tapes, see `TuringMachine::run_on`
+    fn run_on_tapes(tapes: &mut Self::Tapes) -> bool
+    where
+        Self: Sized,
+    {
+        let mut machine = Self::from_tapes(mem::take(tapes));
+        let output = machine.run();
+        *tapes = machine.into_tapes();
+        output
+    }
+}
+
+/// Every two-tape machine is a multi-tape machine with its main tape first
+impl<T: TuringMachine> MultiTapeMachine for T {
+    type Tapes = (Tape<T::Main>, Tape<T::Aux>);
+
+    fn from_tapes((main_tape, aux_tape): Self::Tapes) -> Self {
+        T::new(main_tape, aux_tape)
+    }
+
+    fn run(&mut self) -> bool {
+        TuringMachine::run(self)
+    }
+
+    fn into_tapes(self) -> Self::Tapes {
+        self.destroy().into_tapes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::TapeBuilder,
+        cost::{with_cost_model, CostModel, MovesOnly, PrimitiveOps, Transitions},
+        m1::M1,
+        machine::MainValue,
+        tape::Move::{Left, Right, Stay},
+        test_support::Bit,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    /// Writes the bitwise xor of the first two tapes onto the third, moving all three heads
+    /// together. Rejects if the first two words differ in length.
+    struct Xor {
+        tapes: (Tape<Bit>, Tape<Bit>, Tape<Bit>),
+    }
+
+    impl MultiTapeMachine for Xor {
+        type Tapes = (Tape<Bit>, Tape<Bit>, Tape<Bit>);
+
+        fn from_tapes(tapes: Self::Tapes) -> Self {
+            Self { tapes }
+        }
+
+        fn run(&mut self) -> bool {
+            loop {
+                self.tapes.move_heads(&[Right, Right, Right]);
+                match self.tapes.read_all() {
+                    (TapeValue::Value(a), TapeValue::Value(b), _) => {
+                        let bit = if a == b { Bit::Zero } else { Bit::One };
+                        self.tapes.2.write(TapeValue::Value(bit));
+                    }
+                    (TapeValue::Empty, TapeValue::Empty, _) => return true,
+                    _ => return false,
+                }
+            }
+        }
+
+        fn into_tapes(self) -> Self::Tapes {
+            self.tapes
+        }
+    }
+
+    /// `Xor` as synchronous transitions
+    fn xor(tapes: &mut (Tape<Bit>, Tape<Bit>, Tape<Bit>)) -> bool {
+        let mut started = false;
+        loop {
+            let accepted = tapes.transition(|symbols| match symbols {
+                _ if !started => Some(((None, None, None), vec![Right; 3], None)),
+                (TapeValue::Value(a), TapeValue::Value(b), _) => {
+                    let bit = if a == b { Bit::Zero } else { Bit::One };
+                    let writes = (None, None, Some(TapeValue::Value(bit)));
+                    Some((writes, vec![Right; 3], None))
+                }
+                (TapeValue::Empty, TapeValue::Empty, _) => {
+                    Some(((None, None, None), vec![Stay; 3], Some(true)))
+                }
+                _ => None,
+            });
+            started = true;
+            match accepted {
+                Some(None) => {}
+                Some(Some(accepted)) => return accepted,
+                None => return false,
+            }
+        }
+    }
+
+    /// Tape of `cells` with the head on the first, counting steps on `steps_ran`
+    fn bits(cells: &str, steps_ran: &Rc<RefCell<u64>>) -> Tape<Bit> {
+        TapeBuilder::new()
+            .steps_ran(Some(Rc::clone(steps_ran)))
+            .head()
+            .cells(cells)
+            .build()
+    }
+
+    fn word<T: Alphabet>(tape: &Tape<T>) -> String {
+        tape.data().iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn three_tapes() {
+        let steps_ran = Rc::new(RefCell::new(0));
+        let mut tapes = (
+            bits("_0110_", &steps_ran),
+            bits("_0101_", &steps_ran),
+            bits("_", &steps_ran),
+        );
+        assert_eq!(tapes.count(), 3);
+        assert!(Xor::run_on_tapes(&mut tapes));
+        assert_eq!(word(&tapes.2), "_0011_");
+        // 5 transitions of 3 moves and 3 reads, and 4 writes
+        assert_eq!(*steps_ran.borrow(), 5 * 6 + 4);
+
+        tapes.move_heads(&[Left, Left, Left]);
+        assert_eq!(
+            tapes.read_all(),
+            (
+                TapeValue::Value(Bit::Zero),
+                TapeValue::Value(Bit::One),
+                TapeValue::Value(Bit::One)
+            )
+        );
+
+        let mut tapes = (
+            bits("_01_", &steps_ran),
+            bits("_0_", &steps_ran),
+            bits("_", &steps_ran),
+        );
+        assert!(!Xor::run_on_tapes(&mut tapes));
+    }
+
+    #[test]
+    fn synchronous() {
+        // 6 transitions: one to move onto the words, one per bit and one to accept
+        let cost_models: [(Rc<dyn CostModel>, _); 3] = [
+            (Rc::new(Transitions), 6),
+            // 3 reads per transition, 3 moves for all but the last and 4 writes
+            (Rc::new(PrimitiveOps), 6 * 3 + 5 * 3 + 4),
+            (Rc::new(MovesOnly), 5 * 3),
+        ];
+        for (cost_model, steps) in cost_models {
+            let steps_ran = Rc::new(RefCell::new(0));
+            let mut tapes = (
+                bits("_0110_", &steps_ran),
+                bits("_0101_", &steps_ran),
+                bits("_", &steps_ran),
+            );
+            assert!(with_cost_model(cost_model, || xor(&mut tapes)));
+            assert_eq!(word(&tapes.2), "_0011_");
+            assert_eq!(*steps_ran.borrow(), steps);
+        }
+
+        // Tapes with their own counters each count the transition
+        let (main_steps, aux_steps) = (Rc::new(RefCell::new(0)), Rc::new(RefCell::new(0)));
+        let mut tapes = (
+            bits("_01_", &main_steps),
+            bits("_0_", &aux_steps),
+            bits("_", &aux_steps),
+        );
+        assert!(!with_cost_model(Rc::new(Transitions), || xor(&mut tapes)));
+        assert_eq!((*main_steps.borrow(), *aux_steps.borrow()), (2, 2));
+    }
+
+    #[test]
+    fn two_tapes() {
+        let steps_ran = Rc::new(RefCell::new(0));
+        let mut tapes = M1::input_for("abba", "ab", Some(Rc::clone(&steps_ran)));
+        assert_eq!(tapes.count(), 2);
+        assert!(M1::run_on_tapes(&mut tapes));
+        assert_eq!(tapes.0.read(), TapeValue::Value(MainValue::Hash));
+    }
+
+    /// Inverts every bit on each of any number of tapes, moving all heads together. Rejects if
+    /// the words differ in length.
+    struct Invert {
+        tapes: Vec<Tape<Bit>>,
+    }
+
+    impl MultiTapeMachine for Invert {
+        type Tapes = Vec<Tape<Bit>>;
+
+        fn from_tapes(tapes: Self::Tapes) -> Self {
+            Self { tapes }
+        }
+
+        fn run(&mut self) -> bool {
+            let count = self.tapes.count();
+            self.tapes.move_heads(&vec![Right; count]);
+            loop {
+                let accepted = self.tapes.transition(|symbols| {
+                    if symbols.iter().all(|symbol| symbol == &TapeValue::Empty) {
+                        return Some((vec![None; count], vec![Stay; count], Some(true)));
+                    }
+                    let writes = symbols
+                        .into_iter()
+                        .map(|symbol| match symbol {
+                            TapeValue::Value(Bit::Zero) => Some(Some(TapeValue::Value(Bit::One))),
+                            TapeValue::Value(Bit::One) => Some(Some(TapeValue::Value(Bit::Zero))),
+                            // Rejects once one word ends before the others
+                            TapeValue::Empty => None,
+                        })
+                        .collect::<Option<_>>()?;
+                    Some((writes, vec![Right; count], None))
+                });
+                match accepted {
+                    Some(None) => {}
+                    Some(Some(accepted)) => return accepted,
+                    None => return false,
+                }
+            }
+        }
+
+        fn into_tapes(self) -> Self::Tapes {
+            self.tapes
+        }
+    }
+
+    #[test]
+    fn any_number_of_tapes() {
+        let steps_ran = Rc::new(RefCell::new(0));
+        let mut tapes: Vec<Tape<Bit>> = ["_01_", "_11_", "_00_", "_10_", "_01_", "_00_"]
+            .iter()
+            .map(|s| bits(s, &steps_ran))
+            .collect();
+        assert_eq!(tapes.count(), 6);
+        assert!(with_cost_model(Rc::new(Transitions), || {
+            Invert::run_on_tapes(&mut tapes)
+        }));
+        let words: Vec<_> = tapes.iter().map(word).collect();
+        assert_eq!(words, ["_10_", "_00_", "_11_", "_01_", "_10_", "_11_"]);
+        // 6 moves onto the words, then 2 transitions over the bits and 1 to accept
+        assert_eq!(*steps_ran.borrow(), 6 + 3);
+
+        let mut tapes = vec![bits("_01_", &steps_ran), bits("_0_", &steps_ran)];
+        assert!(!Invert::run_on_tapes(&mut tapes));
+    }
+}
@@ -0,0 +1,688 @@
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::{BTreeSet, HashMap},
+    fmt,
+    iter::{Enumerate, Peekable},
+    marker::PhantomData,
+    rc::Rc,
+    str::Chars,
+};
+
+use crate::{
+    alphabet::{Alphabet, EMPTY_CHAR},
+    machine::TuringMachine,
+    tape::{Tape, TapeValue},
+};
+
+/// Cells a single position of a pattern can match
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Class<T: Alphabet> {
+    Any,
+    OneOf(Vec<TapeValue<T>>),
+}
+
+impl<T: Alphabet> Class<T> {
+    fn matches(&self, cell: &TapeValue<T>) -> bool {
+        match self {
+            Self::Any => true,
+            Self::OneOf(cells) => cells.contains(cell),
+        }
+    }
+}
+
+/// Parsed pattern, see `Pattern`
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Node<T: Alphabet> {
+    /// One cell, which must be under the head if `head`
+    Cell {
+        class: Class<T>,
+        head: bool,
+    },
+    Sequence(Vec<Self>),
+    Alternatives(Vec<Self>),
+    /// Any number of repetitions from `min`, unbounded if `max` is `None`
+    Repeat {
+        node: Box<Self>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+/// Shape of a whole tape, over the characters of its alphabet with `_` for an empty cell and `.`
+/// for any cell. `\w` is any symbol that is not a separator and `\W` any separator, see
+/// `Alphabet::is_separator`. `[...]` is the cell under the head, any of the cells inside, while
+/// parentheses, `|`, `*`, `+` and `?` are as in regular expressions and spaces are ignored. For
+/// example `_* [_] (a|b)+ (# (a|b)+)* _*` is a blank head followed by words separated by hashes,
+/// or `_* [_] \w+ (\W \w+)* _*` over any alphabet of words. Only the cells a tape has are matched,
+/// so a pattern must allow for any padding.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Pattern<T: Alphabet> {
+    source: &'static str,
+    node: Node<T>,
+}
+
+/// First cell of a tape that no match of a pattern gets past, or the end of the tape if a match
+/// needs more cells
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Mismatch<T: Alphabet> {
+    pub source: &'static str,
+    pub index: usize,
+    pub cell: Option<TapeValue<T>>,
+}
+
+impl<T: Alphabet> fmt::Display for Mismatch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.cell {
+            Some(cell) => write!(
+                f,
+                "cell {} `{}` does not match `{}`",
+                self.index, cell, self.source
+            ),
+            None => write!(
+                f,
+                "ends at cell {} before matching `{}`",
+                self.index, self.source
+            ),
+        }
+    }
+}
+
+struct Parser<'a, T: Alphabet> {
+    source: &'static str,
+    chars: Peekable<Enumerate<Chars<'a>>>,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T: Alphabet> Parser<'a, T> {
+    fn peek(&mut self) -> Option<char> {
+        while self
+            .chars
+            .next_if(|(_, char)| char.is_whitespace())
+            .is_some()
+        {}
+        self.chars.peek().map(|&(_, char)| char)
+    }
+
+    fn next(&mut self) -> Option<(usize, char)> {
+        self.peek();
+        self.chars.next()
+    }
+
+    fn fail(&self, index: Option<usize>, message: &str) -> ! {
+        match index {
+            Some(index) => panic!("{} at {} in pattern `{}`", message, index, self.source),
+            None => panic!("{} at the end of pattern `{}`", message, self.source),
+        }
+    }
+
+    fn expect(&mut self, expected: char) {
+        match self.next() {
+            Some((_, char)) if char == expected => {}
+            Some((index, _)) => self.fail(Some(index), &format!("Expected `{}`", expected)),
+            None => self.fail(None, &format!("Expected `{}`", expected)),
+        }
+    }
+
+    fn alternatives(&mut self) -> Node<T> {
+        let mut alternatives = vec![self.sequence()];
+        while self.peek() == Some('|') {
+            self.next();
+            alternatives.push(self.sequence());
+        }
+        if alternatives.len() == 1 {
+            return alternatives.pop().unwrap();
+        }
+        // Alternatives of single cells, such as `(a|b)`, are one cell matching any of them
+        let mut cells = vec![];
+        for alternative in &alternatives {
+            match alternative {
+                Node::Cell {
+                    class: Class::OneOf(class),
+                    head: false,
+                } => cells.extend(class.iter().cloned()),
+                _ => return Node::Alternatives(alternatives),
+            }
+        }
+        Node::Cell {
+            class: Class::OneOf(cells),
+            head: false,
+        }
+    }
+
+    fn sequence(&mut self) -> Node<T> {
+        let mut sequence = vec![];
+        while !matches!(self.peek(), None | Some('|' | ')')) {
+            sequence.push(self.repeat());
+        }
+        if sequence.len() == 1 {
+            sequence.pop().unwrap()
+        } else {
+            Node::Sequence(sequence)
+        }
+    }
+
+    fn repeat(&mut self) -> Node<T> {
+        let mut node = self.atom();
+        while let Some(char @ ('*' | '+' | '?')) = self.peek() {
+            self.next();
+            let (min, max) = match char {
+                '*' => (0, None),
+                '+' => (1, None),
+                _ => (0, Some(1)),
+            };
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+            };
+        }
+        node
+    }
+
+    fn atom(&mut self) -> Node<T> {
+        match self.next() {
+            Some((_, '(')) => {
+                let node = self.alternatives();
+                self.expect(')');
+                node
+            }
+            Some((_, '[')) => {
+                let mut cells = vec![];
+                loop {
+                    match self.next() {
+                        Some((_, ']')) if !cells.is_empty() => break,
+                        Some((_, '.')) => {
+                            self.expect(']');
+                            return Node::Cell {
+                                class: Class::Any,
+                                head: true,
+                            };
+                        }
+                        Some((index, '\\')) => cells.extend(self.escape(index)),
+                        Some((index, char)) => cells.push(self.cell(index, char)),
+                        None => self.fail(None, "Expected `]`"),
+                    }
+                }
+                Node::Cell {
+                    class: Class::OneOf(cells),
+                    head: true,
+                }
+            }
+            Some((_, '.')) => Node::Cell {
+                class: Class::Any,
+                head: false,
+            },
+            Some((index, '\\')) => Node::Cell {
+                class: Class::OneOf(self.escape(index)),
+                head: false,
+            },
+            Some((index, char)) => Node::Cell {
+                class: Class::OneOf(vec![self.cell(index, char)]),
+                head: false,
+            },
+            None => self.fail(None, "Expected a cell"),
+        }
+    }
+
+    /// Symbols of `\w` or `\W`, after the backslash at `index`
+    fn escape(&mut self, index: usize) -> Vec<TapeValue<T>> {
+        let (separators, char) = match self.chars.next() {
+            Some((_, char @ 'w')) => (false, char),
+            Some((_, char @ 'W')) => (true, char),
+            _ => self.fail(Some(index), "Expected `\\w` or `\\W`"),
+        };
+        let cells: Vec<_> = T::symbols()
+            .iter()
+            .filter(|symbol| symbol.is_separator() == separators)
+            .cloned()
+            .map(TapeValue::Value)
+            .collect();
+        if cells.is_empty() {
+            self.fail(Some(index), &format!("No symbols for `\\{}`", char));
+        }
+        cells
+    }
+
+    fn cell(&self, index: usize, char: char) -> TapeValue<T> {
+        if char == EMPTY_CHAR {
+            TapeValue::Empty
+        } else {
+            T::from_char(char).map_or_else(
+                || self.fail(Some(index), &format!("Unexpected `{}`", char)),
+                TapeValue::Value,
+            )
+        }
+    }
+}
+
+/// Alphabet and source of a cached pattern
+type PatternKey = (TypeId, &'static str);
+
+impl<T: Alphabet> Pattern<T> {
+    pub fn new(source: &'static str) -> Self {
+        let mut parser = Parser {
+            source,
+            chars: source.chars().enumerate().peekable(),
+            marker: PhantomData,
+        };
+        let node = parser.alternatives();
+        if let Some((index, char)) = parser.next() {
+            parser.fail(Some(index), &format!("Unexpected `{}`", char));
+        }
+        Self { source, node }
+    }
+
+    /// `Pattern::new`, parsed once per thread for each alphabet and source
+    pub fn cached(source: &'static str) -> Rc<Self>
+    where
+        T: 'static,
+    {
+        thread_local! {
+            static PATTERNS: RefCell<HashMap<PatternKey, Rc<dyn Any>>> =
+                RefCell::new(HashMap::new());
+        }
+        let pattern = PATTERNS.with(|patterns| {
+            Rc::clone(
+                patterns
+                    .borrow_mut()
+                    .entry((TypeId::of::<T>(), source))
+                    .or_insert_with(|| Rc::new(Self::new(source))),
+            )
+        });
+        pattern.downcast().unwrap()
+    }
+
+    pub const fn node(&self) -> &Node<T> {
+        &self.node
+    }
+
+    /// Whether every cell of `tape` matches, without counting any steps
+    pub fn matches(&self, tape: &Tape<T>) -> Result<(), Mismatch<T>> {
+        let mut furthest = 0;
+        let starts = Reached {
+            indices: BTreeSet::from([0]),
+            from: None,
+        };
+        if ends(&self.node, tape, starts, &mut furthest).contains(tape.data().len()) {
+            Ok(())
+        } else {
+            Err(Mismatch {
+                source: self.source,
+                index: furthest,
+                cell: tape.data().get(furthest).cloned(),
+            })
+        }
+    }
+}
+
+/// Indices a match can have reached, `indices` and every index from `from` to the end of the tape,
+/// so that runs of any cells are matched without going through the cells
+#[derive(Clone, Default)]
+struct Reached {
+    indices: BTreeSet<usize>,
+    from: Option<usize>,
+}
+
+impl Reached {
+    fn contains(&self, index: usize) -> bool {
+        self.indices.contains(&index) || self.from.is_some_and(|from| index >= from)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.indices.is_empty() && self.from.is_none()
+    }
+
+    fn first(&self) -> Option<usize> {
+        self.indices
+            .first()
+            .copied()
+            .into_iter()
+            .chain(self.from)
+            .min()
+    }
+
+    fn union(mut self, other: Self) -> Self {
+        self.indices.extend(other.indices);
+        self.from = self.from.into_iter().chain(other.from).min();
+        self
+    }
+
+    /// Indices not in `other`, keeping any indices from `from` that are not all in `other`
+    fn without(self, other: &Self) -> Self {
+        Self {
+            indices: self
+                .indices
+                .into_iter()
+                .filter(|&index| !other.contains(index))
+                .collect(),
+            from: self
+                .from
+                .filter(|&from| other.from.is_none_or(|other| from < other)),
+        }
+    }
+
+    /// Every index, up to the end of the tape at `len`
+    fn expand(self, len: usize) -> BTreeSet<usize> {
+        let mut indices = self.indices;
+        indices.extend(self.from.map_or(0..0, |from| from..len + 1));
+        indices
+    }
+}
+
+/// Indices after matching `node` from each of `starts`, keeping track of the furthest index that
+/// a cell other than a `.` has matched up to, or failed to match at under the head
+fn ends<T: Alphabet>(
+    node: &Node<T>,
+    tape: &Tape<T>,
+    starts: Reached,
+    furthest: &mut usize,
+) -> Reached {
+    let len = tape.data().len();
+    match node {
+        Node::Cell { class, head: true } => {
+            let head = tape.head();
+            if !starts.contains(head) {
+                return Reached::default();
+            }
+            if class.matches(&tape.data()[head]) {
+                *furthest = (*furthest).max(head + 1);
+                Reached {
+                    indices: BTreeSet::from([head + 1]),
+                    from: None,
+                }
+            } else {
+                *furthest = (*furthest).max(head);
+                Reached::default()
+            }
+        }
+        Node::Cell {
+            class: Class::Any,
+            head: false,
+        } => Reached {
+            indices: starts
+                .indices
+                .into_iter()
+                .filter(|&start| start < len)
+                .map(|start| start + 1)
+                .collect(),
+            from: starts.from.filter(|&from| from < len).map(|from| from + 1),
+        },
+        Node::Cell { class, head: false } => {
+            let indices: BTreeSet<_> = starts
+                .expand(len)
+                .into_iter()
+                .filter(|&start| {
+                    tape.data()
+                        .get(start)
+                        .is_some_and(|cell| class.matches(cell))
+                })
+                .map(|start| start + 1)
+                .collect();
+            *furthest = indices.iter().copied().fold(*furthest, usize::max);
+            Reached {
+                indices,
+                from: None,
+            }
+        }
+        Node::Sequence(nodes) => nodes
+            .iter()
+            .fold(starts, |starts, node| ends(node, tape, starts, furthest)),
+        Node::Alternatives(nodes) => nodes
+            .iter()
+            .map(|node| ends(node, tape, starts.clone(), furthest))
+            .fold(Reached::default(), Reached::union),
+        Node::Repeat { node, min, max } => {
+            if let Node::Cell {
+                class: class @ Class::OneOf(_),
+                head: false,
+            } = &**node
+            {
+                return runs(class, tape, starts, *min, *max, furthest);
+            }
+            let mut current = starts;
+            for _ in 0..*min {
+                current = ends(node, tape, current, furthest);
+            }
+            if max.is_none()
+                && **node
+                    == (Node::Cell {
+                        class: Class::Any,
+                        head: false,
+                    })
+            {
+                return Reached {
+                    indices: BTreeSet::new(),
+                    from: current.first(),
+                };
+            }
+            let mut all = current.clone();
+            let mut repetitions = *min;
+            while !current.is_empty() && max.is_none_or(|max| repetitions < max) {
+                current = ends(node, tape, current, furthest).without(&all);
+                all = all.union(current.clone());
+                repetitions += 1;
+            }
+            all
+        }
+    }
+}
+
+/// Indices after `min` to `max` cells of `class` from each of `starts`, found from the run of
+/// matching cells after each start rather than one repetition at a time
+fn runs<T: Alphabet>(
+    class: &Class<T>,
+    tape: &Tape<T>,
+    starts: Reached,
+    min: usize,
+    max: Option<usize>,
+    furthest: &mut usize,
+) -> Reached {
+    let mut indices = BTreeSet::new();
+    // Starts inside a run share its end
+    let mut run_end = 0;
+    for start in starts.expand(tape.data().len()) {
+        if start >= run_end {
+            run_end = start
+                + tape
+                    .cells_from(start)
+                    .take_while(|cell| class.matches(cell))
+                    .count();
+        }
+        let end = max.map_or(run_end, |max| run_end.min(start + max));
+        if end > start {
+            *furthest = (*furthest).max(end);
+        }
+        indices.extend(start + min..=end);
+    }
+    Reached {
+        indices,
+        from: None,
+    }
+}
+
+/// Walk of a copy of a tape's head to the right, moving and reading each cell, that checking an
+/// input counts the steps of, as the checks of `M1`, `M2` and `M3` did before they had shapes
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Walk {
+    /// No steps, the cells are checked without moving the head
+    Still,
+    /// Up to the first separator or empty cell, after reading the head if `read_head`
+    Word { read_head: bool },
+    /// Up to the first empty cell, then on to the last cell
+    Tape,
+}
+
+impl Walk {
+    /// Counts the steps of the walk on `tape`, where walking off the end reads an empty cell, see
+    /// `Tape::count_walk`
+    fn count<T: Alphabet>(self, tape: &Tape<T>) {
+        let after_head = tape.data().len() - 1 - tape.head();
+        let up_to = |stop: fn(&TapeValue<T>) -> bool| {
+            tape.cells_from(tape.head() + 1)
+                .position(stop)
+                .unwrap_or(after_head)
+                + 1
+        };
+        match self {
+            Self::Still => {}
+            Self::Word { read_head } => {
+                if read_head {
+                    let _ = tape.read();
+                }
+                tape.count_walk(up_to(|cell| match cell {
+                    TapeValue::Value(symbol) => symbol.is_separator(),
+                    TapeValue::Empty => true,
+                }));
+            }
+            Self::Tape => {
+                tape.count_walk(up_to(|cell| cell == &TapeValue::Empty).max(after_head));
+            }
+        }
+    }
+}
+
+/// Machine declaring the shape of its input tapes, as patterns its `new` checks them against
+pub trait InputShape: TuringMachine {
+    const NAME: &'static str;
+    const MAIN_SHAPE: &'static str;
+    const AUX_SHAPE: &'static str;
+    const MAIN_WALK: Walk;
+    const AUX_WALK: Walk;
+
+    /// Panics at the first cell of either tape that does not match its shape, and otherwise counts
+    /// the steps of the walk over each
+    fn check_input(main_tape: &Tape<Self::Main>, aux_tape: &Tape<Self::Aux>) {
+        if let Err(mismatch) = Pattern::cached(Self::MAIN_SHAPE).matches(main_tape) {
+            panic!("{} main tape {}", Self::NAME, mismatch);
+        }
+        if let Err(mismatch) = Pattern::cached(Self::AUX_SHAPE).matches(aux_tape) {
+            panic!("{} aux tape {}", Self::NAME, mismatch);
+        }
+        Self::MAIN_WALK.count(main_tape);
+        Self::AUX_WALK.count(aux_tape);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::TapeBuilder,
+        m1::M1,
+        m2::M2,
+        m3::M3,
+        machine::{AuxValue, MainValue},
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    fn matches(pattern: &'static str, s: &str) -> Result<(), (usize, Option<char>)> {
+        let steps_ran = Rc::new(RefCell::new(0));
+        let tape: Tape<MainValue> = TapeBuilder::new()
+            .steps_ran(Some(Rc::clone(&steps_ran)))
+            .head()
+            .cells(s)
+            .build();
+        let output = Pattern::new(pattern).matches(&tape).map_err(|mismatch| {
+            (
+                mismatch.index,
+                mismatch
+                    .cell
+                    .map(|cell| cell.to_string().chars().next().unwrap()),
+            )
+        });
+        assert_eq!(*steps_ran.borrow(), 0);
+        output
+    }
+
+    #[test]
+    fn patterns() {
+        // The head is on the first cell of these tapes
+        let words = "[_] (a|b)+ (# (a|b)+)* _*";
+        assert_eq!(matches(words, "_ab#b"), Ok(()));
+        assert_eq!(matches(words, "_ab#b__"), Ok(()));
+        assert_eq!(matches(words, "_ab##b"), Err((4, Some('#'))));
+        assert_eq!(matches(words, "_ab#"), Err((4, None)));
+        assert_eq!(matches(words, "a"), Err((0, Some('a'))));
+        assert_eq!(matches("_ [_]", "__"), Err((1, Some('_'))));
+        assert_eq!(matches("[#a] b? .", "ab#"), Ok(()));
+        assert_eq!(matches("[#a] b? .", "a#"), Ok(()));
+        assert_eq!(matches("[.] a?", "b"), Ok(()));
+        assert_eq!(matches(".* [#] a .*", "#ab#"), Ok(()));
+        assert_eq!(matches(".* [#] a .*", "#b"), Err((1, Some('b'))));
+        assert_eq!(matches(".* [#] a .*", "a#"), Err((0, Some('a'))));
+        assert_eq!(matches(r"[\W] \w+ (\W|_)", "#ab_"), Ok(()));
+        assert_eq!(matches(r"[\W] \w+ (\W|_)", "#a#b"), Err((3, Some('b'))));
+        assert_eq!(matches(r"[\w]", "#"), Err((0, Some('#'))));
+    }
+
+    #[test]
+    #[should_panic(expected = "No symbols for `\\W` at 0 in pattern `\\W`")]
+    fn no_separators() {
+        Pattern::<AuxValue>::new(r"\W");
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected `c` at 3 in pattern `(a|c)`")]
+    fn not_a_symbol() {
+        Pattern::<MainValue>::new("(a|c)");
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected `)` at the end of pattern `(a|b`")]
+    fn unclosed() {
+        Pattern::<MainValue>::new("(a|b");
+    }
+
+    #[test]
+    fn terminators() {
+        // M1 takes the end of its word from an empty cell, M2 only from a hash
+        let main_tape = TapeBuilder::new()
+            .head()
+            .separator()
+            .word("ab")
+            .blank()
+            .build();
+        let aux_tape = TapeBuilder::new().head().blank().word("a").blank().build();
+        M1::check_input(&main_tape, &aux_tape);
+        let main_tape: Tape<MainValue> =
+            TapeBuilder::new().head().blank().word("ab").blank().build();
+        let mismatch = Pattern::new(M2::MAIN_SHAPE)
+            .matches(&main_tape)
+            .unwrap_err();
+        assert_eq!((mismatch.index, mismatch.cell), (3, Some(TapeValue::Empty)));
+    }
+
+    #[test]
+    fn walks() {
+        // Steps of the checks M1, M2 and M3 made by walking copies of their tapes
+        let steps_ran = Rc::new(RefCell::new(0));
+        let (main_tape, aux_tape) = M1::input_for("abba", "ab", Some(Rc::clone(&steps_ran)));
+        M1::check_input(&main_tape, &aux_tape);
+        assert_eq!(steps_ran.replace(0), 1 + 2 * 5 + 2 * 3);
+        let (main_tape, aux_tape) = M2::input_for("ab", Some(Rc::clone(&steps_ran)));
+        M2::check_input(&main_tape, &aux_tape);
+        assert_eq!(steps_ran.replace(0), 2 * 3);
+        let (main_tape, aux_tape) = M3::input_for(&["ab", "ab", "ab"], Some(Rc::clone(&steps_ran)));
+        M3::check_input(&main_tape, &aux_tape);
+        assert_eq!(steps_ran.replace(0), 2 * 9);
+
+        // M3 walks on over the empty cells after its words
+        let main_tape = TapeBuilder::new()
+            .steps_ran(Some(Rc::clone(&steps_ran)))
+            .head()
+            .blank()
+            .words(&["ab", "ab"])
+            .blanks(3)
+            .build();
+        M3::check_input(&main_tape, &aux_tape);
+        assert_eq!(*steps_ran.borrow(), 2 * 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "M3 main tape cell 4 `#` does not match")]
+    fn check_input() {
+        let (main_tape, aux_tape) = M3::input_for(&["ab", "", "b"], None);
+        M3::new(main_tape, aux_tape);
+    }
+}
@@ -0,0 +1,460 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    iter,
+    rc::Rc,
+};
+
+use crate::{
+    alphabet::Alphabet,
+    m1::M1,
+    multi_tape::StepCounting,
+    table::{Rule, Table},
+    tape::{Move, Tape, TapeConstructor, TapeValue},
+    tracks::{Mark, Track},
+};
+
+/// Cell of a single tape holding both tapes of a two-tape machine on separate tracks, each
+/// followed by a track marking where that tape's head is
+pub type Cell<M, A> = (TapeValue<M>, TapeValue<Mark>, TapeValue<A>, TapeValue<Mark>);
+
+const fn mark(head: bool) -> TapeValue<Mark> {
+    if head {
+        TapeValue::Value(Mark)
+    } else {
+        TapeValue::Empty
+    }
+}
+
+/// Single-tape table machine simulating a two-tape table machine, built from its transitions. Each
+/// simulated transition sweeps right from the left end of the used cells to find both heads, then
+/// goes back to update each track, so takes steps linear in the cells used. The simulation of a
+/// nondeterministic table makes the same choices as the table.
+pub struct SingleTape<M: Alphabet, A: Alphabet> {
+    /// Runs on the single tape, with a second tape it never reads, writes or moves
+    table: Table<Cell<M, A>, Mark>,
+}
+
+/// Head of the simulated table
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum Head {
+    Main,
+    Aux,
+}
+
+impl Head {
+    /// Whether `cell` is under this head
+    fn marks<M: Alphabet, A: Alphabet>(self, cell: &Cell<M, A>) -> bool {
+        let head = match self {
+            Self::Main => Track::<1>::track(cell),
+            Self::Aux => Track::<3>::track(cell),
+        };
+        *head == mark(true)
+    }
+
+    /// Write and move of `rule` for this head
+    const fn step<M: Alphabet, A: Alphabet>(self, rule: &Rule<M, A>) -> (bool, Move) {
+        match self {
+            Self::Main => (rule.write.0.is_some(), rule.moves.0),
+            Self::Aux => (rule.write.1.is_some(), rule.moves.1),
+        }
+    }
+
+    /// Writes `rule`'s write for this head to `cell` if given, and marks or unmarks the head
+    fn edit<M: Alphabet, A: Alphabet>(
+        self,
+        cell: &mut Cell<M, A>,
+        rule: Option<&Rule<M, A>>,
+        head: bool,
+    ) {
+        match self {
+            Self::Main => edit::<_, 0, 1>(cell, rule.and_then(|rule| rule.write.0.as_ref()), head),
+            Self::Aux => edit::<_, 2, 3>(cell, rule.and_then(|rule| rule.write.1.as_ref()), head),
+        }
+    }
+}
+
+/// Writes `write` to track `SYMBOL` of `cell` if given, and `head` to its head mark on track `HEAD`
+fn edit<T, const SYMBOL: usize, const HEAD: usize>(
+    cell: &mut T,
+    write: Option<&TapeValue<<T as Track<SYMBOL>>::Symbol>>,
+    head: bool,
+) where
+    T: Track<SYMBOL> + Track<HEAD, Symbol = Mark>,
+{
+    if let Some(value) = write {
+        *Track::<SYMBOL>::track_mut(cell) = value.clone();
+    }
+    *Track::<HEAD>::track_mut(cell) = mark(head);
+}
+
+/// What a state of the single-tape machine does towards simulating a transition
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+enum Phase<M: Alphabet, A: Alphabet> {
+    /// Goes left to the empty cell before the used cells, then right into the phase it holds
+    Rewind(Box<Self>),
+    /// Goes right in the simulated `state` until it has found the symbol under each head
+    Find {
+        state: u32,
+        main: Option<TapeValue<M>>,
+        aux: Option<TapeValue<A>>,
+    },
+    /// Goes right to the cell marked for `Head` to write and move it as the transition says
+    Update(Rule<M, A>, Head),
+    /// Marks `Head` on the cell the transition moved it to
+    Mark(Rule<M, A>, Head),
+    Accept,
+}
+
+impl<M: Alphabet, A: Alphabet> SingleTape<M, A> {
+    /// Builds the transitions out of every state reachable from the start of `table`, counting
+    /// steps as `table` does
+    pub fn new(table: &Table<M, A>) -> Self {
+        let start = enter(table, table.start());
+        let mut states = HashMap::from([(Phase::Accept, 0), (start.clone(), 1)]);
+        let mut queue = VecDeque::from([start.clone()]);
+        let mut rules = vec![];
+        let cells = cells();
+        while let Some(phase) = queue.pop_front() {
+            for cell in &cells {
+                for (next, write, direction) in transitions(table, &phase, cell) {
+                    let count = u32::try_from(states.len()).expect("Too many states");
+                    let next = *states.entry(next.clone()).or_insert_with(|| {
+                        queue.push_back(next);
+                        count
+                    });
+                    rules.push(Rule::new(
+                        states[&phase],
+                        (Some(cell.clone()), None),
+                        next,
+                        (write, None),
+                        (direction, Move::Stay),
+                    ));
+                }
+            }
+        }
+
+        let single_tape = Table::new("Single tape", states[&start], states[&Phase::Accept], rules);
+        Self {
+            table: match table.step_counting() {
+                Some(counting) => single_tape.with_step_counting(counting),
+                None => single_tape,
+            },
+        }
+    }
+
+    /// Lays the two tapes out cell by cell on a single tape, with the head on an empty cell to the
+    /// left of them
+    pub fn encode(
+        main_tape: &Tape<M>,
+        aux_tape: &Tape<A>,
+        steps_ran: Option<Rc<RefCell<u64>>>,
+    ) -> Tape<Cell<M, A>> {
+        let length = main_tape.data().len().max(aux_tape.data().len());
+        let mut data = vec![TapeConstructor::Head(TapeValue::Empty)];
+        for index in 0..length {
+            data.push(TapeConstructor::Value(TapeValue::Value((
+                main_tape.data().get(index).cloned().unwrap_or_default(),
+                mark(index == main_tape.head()),
+                aux_tape.data().get(index).cloned().unwrap_or_default(),
+                mark(index == aux_tape.head()),
+            ))));
+        }
+        Tape::new(data, steps_ran)
+    }
+
+    /// Splits a single tape back into its two tapes, without counting steps
+    pub fn decode(tape: &Tape<Cell<M, A>>) -> (Tape<M>, Tape<A>) {
+        let mut main_data = vec![];
+        let mut aux_data = vec![];
+        for cell in tape.data() {
+            let (main, main_head, aux, aux_head) = match cell {
+                TapeValue::Value(cell) => cell.clone(),
+                TapeValue::Empty => Cell::default(),
+            };
+            main_data.push(if main_head == TapeValue::Empty {
+                TapeConstructor::Value(main)
+            } else {
+                TapeConstructor::Head(main)
+            });
+            aux_data.push(if aux_head == TapeValue::Empty {
+                TapeConstructor::Value(aux)
+            } else {
+                TapeConstructor::Head(aux)
+            });
+        }
+        (
+            Tape::new(main_data, tape.steps_ran_counter()),
+            Tape::new(aux_data, tape.steps_ran_counter()),
+        )
+    }
+
+    /// Runs the simulation on a tape laid out by `encode`
+    pub fn run_on(&self, tape: &mut Tape<Cell<M, A>>) -> bool {
+        self.table.run_on(tape, &mut Tape::default())
+    }
+}
+
+/// Every symbol a cell can hold, and the empty cell
+fn values<T: Alphabet>() -> impl Iterator<Item = TapeValue<T>> + Clone {
+    iter::once(TapeValue::Empty).chain(T::symbols().iter().cloned().map(TapeValue::Value))
+}
+
+/// Every cell of the single tape
+fn cells<M: Alphabet, A: Alphabet>() -> Vec<TapeValue<Cell<M, A>>> {
+    let mut cells = vec![TapeValue::Empty];
+    for main in values::<M>() {
+        for aux in values::<A>() {
+            for (main_head, aux_head) in
+                [(false, false), (true, false), (false, true), (true, true)]
+            {
+                cells.push(TapeValue::Value((
+                    main.clone(),
+                    mark(main_head),
+                    aux.clone(),
+                    mark(aux_head),
+                )));
+            }
+        }
+    }
+    cells
+}
+
+/// Simulates `state` from wherever the head is, rewinding to the left end of the used cells first
+fn enter<M: Alphabet, A: Alphabet>(table: &Table<M, A>, state: u32) -> Phase<M, A> {
+    if state == table.accept() {
+        Phase::Accept
+    } else {
+        Phase::Rewind(Box::new(Phase::Find {
+            state,
+            main: None,
+            aux: None,
+        }))
+    }
+}
+
+/// Phase after finding both heads for `rule`, or after updating `updated` for it, with the move
+/// into it
+fn after<M: Alphabet, A: Alphabet>(
+    table: &Table<M, A>,
+    rule: &Rule<M, A>,
+    updated: Option<Head>,
+) -> (Phase<M, A>, Move) {
+    let changes = |head: Head| head.step(rule) != (false, Move::Stay);
+    let next = if updated.is_none() && changes(Head::Main) {
+        Phase::Rewind(Box::new(Phase::Update(rule.clone(), Head::Main)))
+    } else if updated != Some(Head::Aux) && changes(Head::Aux) {
+        Phase::Rewind(Box::new(Phase::Update(rule.clone(), Head::Aux)))
+    } else {
+        enter(table, rule.next)
+    };
+    let direction = if matches!(next, Phase::Rewind(_)) {
+        Move::Left
+    } else {
+        Move::Stay
+    };
+    (next, direction)
+}
+
+/// Next phase of a transition of the single-tape machine, with the cell to write and the move
+type Transition<M, A> = (Phase<M, A>, Option<TapeValue<Cell<M, A>>>, Move);
+
+/// Every transition out of `phase` on reading `cell`
+fn transitions<M: Alphabet, A: Alphabet>(
+    table: &Table<M, A>,
+    phase: &Phase<M, A>,
+    cell: &TapeValue<Cell<M, A>>,
+) -> Vec<Transition<M, A>> {
+    let mut value = match (phase, cell) {
+        (Phase::Rewind(then), TapeValue::Empty) => {
+            return vec![((**then).clone(), None, Move::Right)];
+        }
+        (Phase::Rewind(_), TapeValue::Value(_)) => return vec![(phase.clone(), None, Move::Left)],
+        // A head moved onto an empty cell beyond the used cells
+        (Phase::Mark(..), TapeValue::Empty) => Cell::default(),
+        // Every other phase only reads used cells, which are never empty
+        (_, TapeValue::Empty) | (Phase::Accept, _) => return vec![],
+        (_, TapeValue::Value(value)) => value.clone(),
+    };
+    match phase {
+        Phase::Find { state, main, aux } => {
+            let main = main.clone().or_else(|| {
+                Head::Main
+                    .marks(&value)
+                    .then(|| Track::<0>::track(&value).clone())
+            });
+            let aux = aux.clone().or_else(|| {
+                Head::Aux
+                    .marks(&value)
+                    .then(|| Track::<2>::track(&value).clone())
+            });
+            if let (Some(main), Some(aux)) = (&main, &aux) {
+                table
+                    .rules_for(*state, main, aux)
+                    .into_iter()
+                    .map(|rule| {
+                        let (next, direction) = after(table, rule, None);
+                        (next, None, direction)
+                    })
+                    .collect()
+            } else {
+                let state = *state;
+                vec![(Phase::Find { state, main, aux }, None, Move::Right)]
+            }
+        }
+        Phase::Update(rule, head) if head.marks(&value) => {
+            let (_, direction) = head.step(rule);
+            head.edit(&mut value, Some(rule), direction == Move::Stay);
+            let (next, direction) = if direction == Move::Stay {
+                after(table, rule, Some(*head))
+            } else {
+                (Phase::Mark(rule.clone(), *head), direction)
+            };
+            vec![(next, Some(TapeValue::Value(value)), direction)]
+        }
+        Phase::Update(..) => vec![(phase.clone(), None, Move::Right)],
+        Phase::Mark(rule, head) => {
+            head.edit(&mut value, None, true);
+            let (next, direction) = after(table, rule, Some(*head));
+            vec![(next, Some(TapeValue::Value(value)), direction)]
+        }
+        Phase::Rewind(_) | Phase::Accept => unreachable!(),
+    }
+}
+
+/// Steps taken by the `M1` table and by its single-tape simulation on (ab)^pairs, checking that
+/// b^pairs is a subsequence of it, which scans the whole main word. Both count steps as
+/// `counting` says.
+fn m1_steps(pairs: usize, counting: StepCounting) -> (u64, u64) {
+    let table = M1::table().with_step_counting(counting);
+
+    let steps_ran = Rc::new(RefCell::new(0));
+    let (mut main_tape, mut aux_tape) = M1::input_for(
+        &"ab".repeat(pairs),
+        &"b".repeat(pairs),
+        Some(Rc::clone(&steps_ran)),
+    );
+    let single_tape_steps_ran = Rc::new(RefCell::new(0));
+    let mut tape = SingleTape::encode(
+        &main_tape,
+        &aux_tape,
+        Some(Rc::clone(&single_tape_steps_ran)),
+    );
+
+    assert!(table.run_on(&mut main_tape, &mut aux_tape));
+    assert!(SingleTape::new(&table).run_on(&mut tape));
+    let (simulated_main_tape, simulated_aux_tape) = SingleTape::decode(&tape);
+    assert_eq!(simulated_main_tape.canonical(), main_tape.canonical());
+    assert_eq!(simulated_aux_tape.canonical(), aux_tape.canonical());
+    let steps = (*steps_ran.borrow(), *single_tape_steps_ran.borrow());
+    steps
+}
+
+/// Compares the steps taken by the `M1` table with its single-tape simulation as the main word
+/// doubles in length, counting steps as `counting` says
+pub fn report(max_length: usize, counting: StepCounting) {
+    println!("length\ttwo tapes\tone tape\tratio");
+    let mut pairs = 1;
+    while 2 * pairs <= max_length {
+        let (two_tapes, one_tape) = m1_steps(pairs, counting);
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = one_tape as f64 / two_tapes as f64;
+        println!("{}\t{}\t{}\t{:.1}", 2 * pairs, two_tapes, one_tape, ratio);
+        pairs *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::TapeBuilder,
+        chooser::{with_chooser, ScriptedChooser},
+        m2::M2,
+        machine::{AuxValue, MainValue},
+        tape::Move::{Right, Stay},
+        test_support::words,
+    };
+
+    #[test]
+    fn same_as_table() {
+        let table = M1::table();
+        let single_tape = SingleTape::new(&table);
+        for main in words("ab", 1..5) {
+            for aux in words("ab", 1..4) {
+                let (mut main_tape, mut aux_tape) = M1::input_for(&main, &aux, None);
+                let mut tape = SingleTape::encode(&main_tape, &aux_tape, None);
+
+                assert_eq!(
+                    single_tape.run_on(&mut tape),
+                    table.run_on(&mut main_tape, &mut aux_tape),
+                    "{} {}",
+                    main,
+                    aux
+                );
+                let (simulated_main_tape, simulated_aux_tape) = SingleTape::decode(&tape);
+                // The single tape has an extra cell on the left, and pads the shorter tape
+                assert_eq!(simulated_main_tape.canonical(), main_tape.canonical());
+                assert_eq!(simulated_aux_tape.canonical(), aux_tape.canonical());
+            }
+        }
+    }
+
+    #[test]
+    fn nondeterministic() {
+        // M2 guesses each letter, and its simulation guesses the same for the same choices
+        let table = M2::table();
+        let single_tape = SingleTape::new(&table);
+        for script in [[0, 0, 0], [0, 1, 1], [1, 0, 1]] {
+            let chooser = || Rc::new(RefCell::new(ScriptedChooser::new(script.to_vec())));
+            let (mut main_tape, mut aux_tape) = M2::input_for("aba", None);
+            let mut tape = SingleTape::encode(&main_tape, &aux_tape, None);
+            assert!(with_chooser(chooser(), || single_tape.run_on(&mut tape)));
+            assert!(with_chooser(chooser(), || table.run_on(&mut main_tape, &mut aux_tape)));
+            let (_, simulated_aux_tape) = SingleTape::decode(&tape);
+            assert_eq!(simulated_aux_tape.canonical(), aux_tape.canonical());
+        }
+    }
+
+    #[test]
+    fn blank_cells() {
+        // The aux head moves off blank cells it has marked, which must not end the used cells
+        let table = Table::new(
+            "Blank",
+            0,
+            3,
+            vec![
+                Rule::new(0, (None, None), 1, (None, None), (Stay, Right)),
+                Rule::new(1, (None, None), 2, (None, None), (Stay, Right)),
+                Rule::new(2, (None, None), 3, (None, None), (Right, Stay)),
+            ],
+        );
+        let mut main_tape: Tape<MainValue> = TapeBuilder::new().head().blank().build();
+        let mut aux_tape: Tape<AuxValue> = TapeBuilder::new().head().blank().build();
+        let mut tape = SingleTape::encode(&main_tape, &aux_tape, None);
+        assert!(SingleTape::new(&table).run_on(&mut tape));
+        assert!(table.run_on(&mut main_tape, &mut aux_tape));
+        let (simulated_main_tape, simulated_aux_tape) = SingleTape::decode(&tape);
+        assert_eq!(simulated_main_tape.canonical(), main_tape.canonical());
+        assert_eq!(simulated_aux_tape.canonical(), aux_tape.canonical());
+        assert_eq!(tape.to_string(), "____\n__^_\n____\n___^\n  ^");
+    }
+
+    #[test]
+    fn encode() {
+        let (main_tape, aux_tape) = M1::input_for("ab", "a", None);
+        let tape = SingleTape::encode(&main_tape, &aux_tape, None);
+        assert_eq!(tape.to_string(), "_#ab#\n_^___\n__a__\n_^___\n^");
+    }
+
+    #[test]
+    fn quadratic() {
+        // Doubling the input doubles the table's steps but roughly quadruples the simulation's
+        for counting in [StepCounting::PerOperation, StepCounting::PerTransition] {
+            let (two_tapes, one_tape) = m1_steps(8, counting);
+            let (doubled_two_tapes, doubled_one_tape) = m1_steps(16, counting);
+            assert!(doubled_two_tapes < 3 * two_tapes, "{:?}", counting);
+            assert!(doubled_one_tape > 3 * one_tape, "{:?}", counting);
+        }
+    }
+}
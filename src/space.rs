@@ -0,0 +1,127 @@
+use std::fmt;
+
+/// Cells a tape has used so far, one flag per cell of the tape
+#[derive(Clone, Debug, Default)]
+pub struct Usage {
+    /// Index of the cell the head started on, which moves right as the tape grows left
+    origin: usize,
+    visited: Vec<bool>,
+    written: Vec<bool>,
+}
+
+impl Usage {
+    pub fn new(origin: usize) -> Self {
+        let mut usage = Self {
+            origin,
+            ..Self::default()
+        };
+        usage.visit(origin);
+        usage
+    }
+
+    /// An empty cell has been added before the first one
+    pub fn grown_left(&mut self) {
+        self.origin += 1;
+        self.visited.insert(0, false);
+        self.written.insert(0, false);
+    }
+
+    /// The head has moved onto the cell at `index`
+    pub fn visit(&mut self, index: usize) {
+        set(&mut self.visited, index);
+    }
+
+    /// The cell at `index` has been written
+    pub fn write(&mut self, index: usize) {
+        set(&mut self.written, index);
+    }
+
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn space(&self) -> Space {
+        let position =
+            |index: Option<usize>| index.unwrap_or(self.origin) as isize - self.origin as isize;
+        Space {
+            visited: self.visited.iter().filter(|&&visited| visited).count(),
+            leftmost: position(self.visited.iter().position(|&visited| visited)),
+            rightmost: position(self.visited.iter().rposition(|&visited| visited)),
+            written: self.written.iter().filter(|&&written| written).count(),
+        }
+    }
+}
+
+fn set(flags: &mut Vec<bool>, index: usize) {
+    if flags.len() <= index {
+        flags.resize(index + 1, false);
+    }
+    flags[index] = true;
+}
+
+/// Space a tape has used, with head positions relative to the cell its head started on
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Space {
+    /// Cells the head has been on
+    pub visited: usize,
+    pub leftmost: isize,
+    pub rightmost: isize,
+    /// Cells written at least once
+    pub written: usize,
+}
+
+impl fmt::Display for Space {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} visited from {} to {}, {} written",
+            self.visited, self.leftmost, self.rightmost, self.written
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::TapeBuilder,
+        m1::M1,
+        machine::{MainValue, TuringMachine},
+        tape::{Tape, TapeValue},
+    };
+
+    #[test]
+    fn space() {
+        let mut tape: Tape<MainValue> = TapeBuilder::new().head().blank().word("a").build();
+        tape.right();
+        tape.right();
+        tape.write(TapeValue::Value(MainValue::B));
+        tape.write(TapeValue::Value(MainValue::A));
+        tape.left();
+        tape.left();
+        tape.left();
+        tape.write(TapeValue::Value(MainValue::Hash));
+        assert_eq!(
+            tape.space(),
+            Space {
+                visited: 4,
+                leftmost: -1,
+                rightmost: 2,
+                written: 2,
+            }
+        );
+        assert_eq!(
+            tape.space().to_string(),
+            "4 visited from -1 to 2, 2 written"
+        );
+    }
+
+    #[test]
+    fn m1() {
+        // M1 scans the main word and the aux word once each, writing nothing
+        let (main_tape, aux_tape) = M1::input_for("abba", "ab", None);
+        let mut m1 = M1::new(main_tape, aux_tape);
+        assert!(m1.run());
+        let (main_tape, aux_tape) = m1.destroy().into_tapes();
+        let (main, aux) = (main_tape.space(), aux_tape.space());
+        assert_eq!((main.leftmost, main.rightmost, main.written), (0, 5, 0));
+        assert_eq!((aux.leftmost, aux.rightmost, aux.written), (0, 3, 0));
+    }
+}
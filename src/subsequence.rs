@@ -0,0 +1,241 @@
+use crate::{
+    alphabet::{Alphabet, WordAlphabet},
+    call_stack,
+    generate::Oracle,
+    machine::{DestroyOutput, TuringMachine},
+    pattern::{InputShape, Walk},
+    tape::{Tape, TapeValue},
+};
+
+/// Accepts if the word on the aux tape is a subsequence of the word on the main tape, over any word
+/// alphabet. `M1` is this over `MainValue`. Rejects as soon as a move crashes or violates a bound,
+/// see `Tape::has_halted`.
+pub struct Subsequence<M: WordAlphabet> {
+    main_tape: Tape<M>,
+    aux_tape: Tape<M::Letter>,
+}
+
+impl<M: WordAlphabet> TuringMachine for Subsequence<M> {
+    type Main = M;
+    type Aux = M::Letter;
+
+    fn new(main_tape: Tape<M>, aux_tape: Tape<M::Letter>) -> Self {
+        Self::check_input(&main_tape, &aux_tape);
+
+        Self {
+            main_tape,
+            aux_tape,
+        }
+    }
+
+    fn run(&mut self) -> bool {
+        let _invocation = call_stack::enter(Self::NAME, self.main_tape.steps_ran_counter());
+        self.zero()
+    }
+
+    fn destroy(self) -> DestroyOutput<M, M::Letter> {
+        DestroyOutput::new(self.main_tape, self.aux_tape)
+    }
+}
+
+/// Word after a separator under the head, ended by a separator or an empty cell, and a word after
+/// an empty head
+impl<M: WordAlphabet> InputShape for Subsequence<M> {
+    const NAME: &'static str = M::MACHINE_NAMES.subsequence;
+    const MAIN_SHAPE: &'static str = r".* [\W] \w+ ((\W|_) .*)?";
+    const AUX_SHAPE: &'static str = r"_* [_] \w+ _*";
+    const MAIN_WALK: Walk = Walk::Word { read_head: true };
+    const AUX_WALK: Walk = Walk::Tape;
+}
+
+/// Accepts if the aux word is a subsequence of the main word, matching each letter as early as it
+/// can
+impl<M: WordAlphabet> Oracle for Subsequence<M> {
+    fn accepts(main_tape: &Tape<M>, aux_tape: &Tape<M::Letter>) -> bool {
+        let word = main_tape
+            .words_from(main_tape.head() + 1)
+            .next()
+            .map(|word| word.letters())
+            .unwrap_or_default();
+        let mut letters = word.iter();
+        aux_tape
+            .word_at(aux_tape.head() + 1)
+            .symbols()
+            .iter()
+            .all(|letter| letters.any(|main_letter| main_letter == letter))
+    }
+}
+
+// Run
+impl<M: WordAlphabet> Subsequence<M> {
+    /// States 0 to 13 are those of `M1`, with a scan for each letter, see `scan`
+    fn zero(&mut self) -> bool {
+        // 0
+        call_stack::state(0);
+        self.aux_tape.right();
+        if self.aux_tape.has_halted() {
+            return false;
+        }
+        // 1
+        call_stack::state(1);
+        match self.aux_tape.read() {
+            TapeValue::Value(letter) => {
+                // Scan
+                self.scan(&letter)
+            }
+            TapeValue::Empty => {
+                // 4
+                self.four()
+            }
+        }
+    }
+
+    /// States 2 and 3 for the first letter and 6 and 7 for the second, as in `M1`, then 14 and
+    /// 15 for the third, 16 and 17 for the fourth and so on
+    fn scan(&mut self, letter: &M::Letter) -> bool {
+        let index = M::Letter::symbols()
+            .iter()
+            .position(|symbol| symbol == letter)
+            .unwrap();
+        let state = match index {
+            0 => 2,
+            1 => 6,
+            _ => 10 + 2 * u32::try_from(index).unwrap(),
+        };
+        call_stack::state(state);
+        self.main_tape.right();
+        if self.main_tape.has_halted() {
+            return false;
+        }
+        call_stack::state(state + 1);
+        match self.main_tape.read() {
+            TapeValue::Value(symbol) => match symbol.letter() {
+                Some(symbol) if &symbol == letter => {
+                    // 0
+                    self.zero()
+                }
+                Some(_) => {
+                    // Scan
+                    self.scan(letter)
+                }
+                None => {
+                    // 8
+                    self.eight()
+                }
+            },
+            TapeValue::Empty => {
+                // 8
+                self.eight()
+            }
+        }
+    }
+
+    fn four(&mut self) -> bool {
+        // 4
+        call_stack::state(4);
+        self.aux_tape.left();
+        if self.aux_tape.has_halted() {
+            return false;
+        }
+        // 5
+        call_stack::state(5);
+        match self.aux_tape.read() {
+            TapeValue::Value(_) => {
+                // 4
+                self.four()
+            }
+            TapeValue::Empty => {
+                // 11
+                self.eleven()
+            }
+        }
+    }
+
+    fn eight(&mut self) -> bool {
+        // 8
+        call_stack::state(8);
+        self.aux_tape.left();
+        if self.aux_tape.has_halted() {
+            return false;
+        }
+        // 9
+        call_stack::state(9);
+        match self.aux_tape.read() {
+            TapeValue::Value(_) => {
+                // 8
+                self.eight()
+            }
+            TapeValue::Empty => {
+                // 10
+                call_stack::state(10);
+                false
+            }
+        }
+    }
+
+    fn eleven(&mut self) -> bool {
+        // 11
+        call_stack::state(11);
+        self.main_tape.right();
+        if self.main_tape.has_halted() {
+            return false;
+        }
+        // 12
+        call_stack::state(12);
+        match self.main_tape.read() {
+            TapeValue::Value(symbol) if symbol.letter().is_some() => {
+                // 11
+                self.eleven()
+            }
+            TapeValue::Value(_) | TapeValue::Empty => {
+                // 13
+                call_stack::state(13);
+                true
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builder::TapeBuilder, machine::MainValue, test_support::Strand};
+    use std::{cell::RefCell, rc::Rc};
+
+    /// Runs on `main` between separators and `aux` after an empty head, as `M1::input_for` lays
+    /// them out
+    fn run<M: WordAlphabet>(main: &str, aux: &str) -> (bool, u64) {
+        let steps_ran = Rc::new(RefCell::new(0));
+        let mut machine = Subsequence::<M>::new(
+            TapeBuilder::new()
+                .steps_ran(Some(Rc::clone(&steps_ran)))
+                .head()
+                .separator()
+                .word(main)
+                .separator()
+                .build(),
+            TapeBuilder::new()
+                .steps_ran(Some(Rc::clone(&steps_ran)))
+                .head()
+                .blank()
+                .word(aux)
+                .blank()
+                .build(),
+        );
+        let output = machine.run();
+        let steps_ran = *steps_ran.borrow();
+        (output, steps_ran)
+    }
+
+    #[test]
+    fn bases() {
+        assert!(run::<Strand>("gattaca", "gtc").0);
+        assert!(run::<Strand>("gattaca", "gattaca").0);
+        assert!(!run::<Strand>("gattaca", "gact").0);
+        // Steps grow with the main word, not the alphabet
+        assert_eq!(
+            run::<Strand>("gattaca", "gc").1,
+            run::<MainValue>("aaaaaba", "ab").1
+        );
+    }
+}
@@ -0,0 +1,422 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap},
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    call_stack, cost,
+    machine::{choose_among, Outcome},
+    multi_tape::{StepCounting, TapeSet},
+    tape::{Canonical, LeftEnd, Move, Tape, TapeValue},
+    tracks::Tracks,
+};
+
+/// Transition of a two-tape table machine. A `None` read matches any symbol and a `None` write
+/// leaves the cell as it is.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Rule<M: Tracks, A: Tracks> {
+    pub state: u32,
+    pub read: (Option<TapeValue<M>>, Option<TapeValue<A>>),
+    pub next: u32,
+    pub write: (Option<TapeValue<M>>, Option<TapeValue<A>>),
+    pub moves: (Move, Move),
+}
+
+impl<M: Tracks, A: Tracks> Rule<M, A> {
+    pub const fn new(
+        state: u32,
+        read: (Option<TapeValue<M>>, Option<TapeValue<A>>),
+        next: u32,
+        write: (Option<TapeValue<M>>, Option<TapeValue<A>>),
+        moves: (Move, Move),
+    ) -> Self {
+        Self {
+            state,
+            read,
+            next,
+            write,
+            moves,
+        }
+    }
+
+    /// Writes then moves each head, counting each operation
+    pub fn apply(&self, main_tape: &mut Tape<M>, aux_tape: &mut Tape<A>) {
+        if let Some(value) = &self.write.0 {
+            main_tape.write(value.clone());
+        }
+        if let Some(value) = &self.write.1 {
+            aux_tape.write(value.clone());
+        }
+        main_tape.move_head(self.moves.0);
+        aux_tape.move_head(self.moves.1);
+    }
+
+    fn matches(&self, state: u32, main: &TapeValue<M>, aux: &TapeValue<A>) -> bool {
+        self.state == state
+            && self.read.0.as_ref().is_none_or(|read| read == main)
+            && self.read.1.as_ref().is_none_or(|read| read == aux)
+    }
+}
+
+/// Two-tape machine given by its transitions rather than by code. Each transition reads both
+/// heads, then writes and moves each of them, as one synchronous transition. The machine accepts
+/// on reaching `accept` and rejects when no transition matches. When several transitions match,
+/// the machine is nondeterministic and one is picked with `choose_among`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Table<M: Tracks, A: Tracks> {
+    name: &'static str,
+    start: u32,
+    accept: u32,
+    /// Transitions out of each state, in the order given
+    rules: BTreeMap<u32, Vec<Rule<M, A>>>,
+    detect_loops: bool,
+    counting: Option<StepCounting>,
+}
+
+impl<M: Tracks, A: Tracks> Table<M, A> {
+    pub fn new(name: &'static str, start: u32, accept: u32, rules: Vec<Rule<M, A>>) -> Self {
+        assert!(
+            rules.iter().all(|rule| rule.state != accept),
+            "Table must not have transitions out of the accept state"
+        );
+        let mut by_state = BTreeMap::<_, Vec<_>>::new();
+        for rule in rules {
+            by_state.entry(rule.state).or_default().push(rule);
+        }
+        let rules = by_state;
+        Self {
+            name,
+            start,
+            accept,
+            rules,
+            detect_loops: false,
+            counting: None,
+        }
+    }
+
+    /// Halts with `Outcome::Looping` on revisiting a configuration, which for a deterministic
+    /// table means it would never halt. Configurations are told apart by a 64-bit hash, so a
+    /// collision could report a loop that is not there, with negligible odds.
+    #[must_use]
+    #[allow(dead_code)]
+    pub fn with_loop_detection(mut self) -> Self {
+        assert!(
+            self.is_deterministic(),
+            "{} must be deterministic to detect loops",
+            self.name
+        );
+        self.detect_loops = true;
+        self
+    }
+
+    /// Counts each transition as `counting` says, rather than under the current cost model
+    #[must_use]
+    pub const fn with_step_counting(mut self, counting: StepCounting) -> Self {
+        self.counting = Some(counting);
+        self
+    }
+
+    #[cfg(test)]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// How the table counts its transitions, if not under the current cost model
+    pub const fn step_counting(&self) -> Option<StepCounting> {
+        self.counting
+    }
+
+    pub const fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub const fn accept(&self) -> u32 {
+        self.accept
+    }
+
+    /// Every transition out of `state` on reading `main` and `aux`
+    pub fn rules_for(
+        &self,
+        state: u32,
+        main: &TapeValue<M>,
+        aux: &TapeValue<A>,
+    ) -> Vec<&Rule<M, A>> {
+        self.rules.get(&state).map_or_else(Vec::new, |rules| {
+            rules
+                .iter()
+                .filter(|rule| rule.matches(state, main, aux))
+                .collect()
+        })
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.rules.values().all(|rules| {
+            rules.iter().enumerate().all(|(index, rule)| {
+                rules[index + 1..].iter().all(|other| {
+                    !overlaps(&rule.read.0, &other.read.0) || !overlaps(&rule.read.1, &other.read.1)
+                })
+            })
+        })
+    }
+
+    /// Runs the machine on borrowed tapes, see `TuringMachine::run_on`
+    pub fn run_on(&self, main_tape: &mut Tape<M>, aux_tape: &mut Tape<A>) -> bool {
+        self.outcome_on(main_tape, aux_tape) == Outcome::Accept
+    }
+
+    /// Runs the machine on borrowed tapes, halting as soon as it crashes or violates a bound
+    pub fn outcome_on(&self, main_tape: &mut Tape<M>, aux_tape: &mut Tape<A>) -> Outcome {
+        match self.counting {
+            Some(counting) => cost::with_cost_model(counting.cost_model(), || {
+                self.counted_outcome_on(main_tape, aux_tape)
+            }),
+            None => self.counted_outcome_on(main_tape, aux_tape),
+        }
+    }
+
+    /// `outcome_on` under whichever cost model is current
+    fn counted_outcome_on(&self, main_tape: &mut Tape<M>, aux_tape: &mut Tape<A>) -> Outcome {
+        let _invocation = call_stack::enter(self.name, main_tape.steps_ran_counter());
+        let mut state = self.start;
+        let mut seen = HashMap::new();
+        for transitions in 0_u64.. {
+            call_stack::state(state);
+            if state == self.accept {
+                return Outcome::Accept;
+            }
+            if self.detect_loops {
+                // Only a hash of each configuration is kept, as whole tapes would take memory
+                // quadratic in the transitions
+                let mut hasher = DefaultHasher::new();
+                (state, configuration(main_tape), configuration(aux_tape)).hash(&mut hasher);
+                if let Some(first) = seen.insert(hasher.finish(), transitions) {
+                    return Outcome::Looping {
+                        cycle: transitions - first,
+                    };
+                }
+            }
+            let next = (&mut *main_tape, &mut *aux_tape).transition(|(main, aux)| {
+                let rules = self.rules_for(state, &main, &aux);
+                let rule = match rules.len() {
+                    0 => return None,
+                    1 => rules[0],
+                    count => rules[choose_among(count)],
+                };
+                Some((
+                    rule.write.clone(),
+                    vec![rule.moves.0, rule.moves.1],
+                    rule.next,
+                ))
+            });
+            state = if let Some(next) = next {
+                next
+            } else {
+                return Outcome::Reject;
+            };
+            if let Some(outcome) =
+                Outcome::of_tape(main_tape).or_else(|| Outcome::of_tape(aux_tape))
+            {
+                return outcome;
+            }
+        }
+        unreachable!()
+    }
+}
+
+/// Configuration of `tape` up to padding added by moving its head. A machine behaves the same
+/// wherever its tape is, unless the tape is bounded, so only then is the head position kept.
+fn configuration<T: Tracks>(tape: &Tape<T>) -> (Canonical<T>, Option<usize>) {
+    let bounded = tape.left_end() != LeftEnd::Grow || tape.has_end_markers();
+    (tape.canonical(), bounded.then(|| tape.head()))
+}
+
+/// Whether two reads can match the same symbol
+fn overlaps<T: Clone + PartialEq>(
+    read: &Option<TapeValue<T>>,
+    other: &Option<TapeValue<T>>,
+) -> bool {
+    match (read, other) {
+        (Some(read), Some(other)) => read == other,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::TapeBuilder,
+        cost::{with_cost_model, CostModel, MovesOnly, PrimitiveOps, Transitions},
+        m1::M1,
+        m2::M2,
+        machine::{AuxValue, MainValue},
+        multi_tape::StepCounting,
+        tape::Move::{Left, Right, Stay},
+        test_support::words,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn left_end() {
+        // Moves left once and accepts on an empty cell
+        let table: Table<MainValue, AuxValue> = Table::new(
+            "Left",
+            0,
+            2,
+            vec![
+                Rule::new(0, (None, None), 1, (None, None), (Left, Stay)),
+                Rule::new(
+                    1,
+                    (Some(TapeValue::Empty), None),
+                    2,
+                    (None, None),
+                    (Stay, Stay),
+                ),
+            ],
+        );
+        for (left_end, outcome) in [
+            (LeftEnd::Grow, Outcome::Accept),
+            (LeftEnd::Stay, Outcome::Reject),
+            (LeftEnd::Crash, Outcome::Crash),
+        ] {
+            let mut main_tape = TapeBuilder::new()
+                .head()
+                .word("a#")
+                .build()
+                .with_left_end(left_end);
+            let mut aux_tape = TapeBuilder::new().head().blank().build();
+            assert_eq!(table.outcome_on(&mut main_tape, &mut aux_tape), outcome);
+        }
+    }
+
+    #[test]
+    fn end_markers() {
+        // M1 accepts a word ended by an empty cell, but needs to move onto it
+        let table = M1::table();
+        for (blanks, outcome) in [(1, Outcome::Accept), (0, Outcome::BoundViolation)] {
+            let mut main_tape = TapeBuilder::new()
+                .head()
+                .separator()
+                .word("ab")
+                .blanks(blanks)
+                .build()
+                .with_end_markers();
+            let mut aux_tape = TapeBuilder::new()
+                .head()
+                .blank()
+                .word("b")
+                .blank()
+                .build()
+                .with_end_markers();
+            assert_eq!(table.outcome_on(&mut main_tape, &mut aux_tape), outcome);
+        }
+    }
+
+    #[test]
+    fn loops() {
+        // Bounces between the first two cells until it reads b
+        let table: Table<MainValue, AuxValue> = Table::new(
+            "Bounce",
+            0,
+            2,
+            vec![
+                Rule::new(
+                    0,
+                    (Some(TapeValue::Value(MainValue::A)), None),
+                    1,
+                    (None, None),
+                    (Right, Stay),
+                ),
+                Rule::new(
+                    0,
+                    (Some(TapeValue::Value(MainValue::B)), None),
+                    2,
+                    (None, None),
+                    (Stay, Stay),
+                ),
+                Rule::new(1, (None, None), 0, (None, None), (Left, Stay)),
+            ],
+        )
+        .with_loop_detection();
+        let steps_ran = Rc::new(RefCell::new(0));
+        let tape = |word| {
+            TapeBuilder::new()
+                .steps_ran(Some(Rc::clone(&steps_ran)))
+                .head()
+                .word(word)
+                .build()
+        };
+        let mut main_tape: Tape<MainValue> = tape("a");
+        let mut aux_tape: Tape<AuxValue> = TapeBuilder::new()
+            .steps_ran(Some(Rc::clone(&steps_ran)))
+            .head()
+            .blank()
+            .build();
+        assert_eq!(
+            with_cost_model(Rc::new(Transitions), || table
+                .outcome_on(&mut main_tape, &mut aux_tape)),
+            Outcome::Looping { cycle: 2 }
+        );
+        // Back on the first cell after 2 transitions, despite the empty cell added on the right
+        assert_eq!(*steps_ran.borrow(), 2);
+
+        let mut main_tape = tape("b");
+        assert_eq!(
+            table.outcome_on(&mut main_tape, &mut aux_tape),
+            Outcome::Accept
+        );
+    }
+
+    #[test]
+    fn no_false_loops() {
+        let table = M1::table().with_loop_detection();
+        for main in words("ab", 1..5) {
+            for aux in words("ab", 1..4) {
+                let (mut main_tape, mut aux_tape) = M1::input_for(&main, &aux, None);
+                let outcome = table.outcome_on(&mut main_tape.clone(), &mut aux_tape.clone());
+                assert_eq!(
+                    outcome,
+                    M1::table().outcome_on(&mut main_tape, &mut aux_tape)
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "M2 table must be deterministic to detect loops")]
+    fn loops_nondeterministic() {
+        let _ = M2::table().with_loop_detection();
+    }
+
+    #[test]
+    fn cost_models() {
+        // 8 transitions, each reading both heads and all but the last moving one
+        let cost_models: [(Rc<dyn CostModel>, _); 3] = [
+            (Rc::new(Transitions), 8),
+            (Rc::new(PrimitiveOps), 8 * 2 + 7),
+            (Rc::new(MovesOnly), 7),
+        ];
+        for (cost_model, steps) in cost_models {
+            let steps_ran = Rc::new(RefCell::new(0));
+            let (mut main_tape, mut aux_tape) =
+                M1::input_for("ab", "b", Some(Rc::clone(&steps_ran)));
+            assert!(with_cost_model(cost_model, || M1::table()
+                .run_on(&mut main_tape, &mut aux_tape)));
+            assert_eq!(*steps_ran.borrow(), steps);
+        }
+
+        // A table's own step counting overrides the current cost model
+        for (counting, steps) in [
+            (StepCounting::PerTransition, 8),
+            (StepCounting::PerOperation, 8 * 2 + 7),
+        ] {
+            let steps_ran = Rc::new(RefCell::new(0));
+            let (mut main_tape, mut aux_tape) =
+                M1::input_for("ab", "b", Some(Rc::clone(&steps_ran)));
+            let table = M1::table().with_step_counting(counting);
+            assert!(with_cost_model(Rc::new(MovesOnly), || table
+                .run_on(&mut main_tape, &mut aux_tape)));
+            assert_eq!(*steps_ran.borrow(), steps);
+        }
+    }
+}
@@ -1,12 +1,24 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+use crate::{
+    cost::{self, Operation},
+    crossing::{Crossing, Crossings},
+    space::{Space, Usage},
+};
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Default)]
 pub enum TapeValue<T: Clone> {
+    #[default]
     Empty,
     Value(T),
 }
 
 #[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case", bound = "T: crate::alphabet::Alphabet")
+)]
 pub enum TapeConstructor<T: Clone> {
     Value(TapeValue<T>),
     Head(TapeValue<T>),
@@ -20,13 +32,101 @@ impl<T: Clone> From<TapeConstructor<T>> for TapeValue<T> {
     }
 }
 
-#[derive(PartialEq, Eq, Debug, Clone)]
+/// Direction to move a head in
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Move {
+    Left,
+    Right,
+    Stay,
+}
+
+/// What moving left off the first cell of a tape does
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum LeftEnd {
+    /// Two-way infinite tape, an empty cell is added
+    #[default]
+    Grow,
+    /// One-way infinite tape, the head stays on the first cell
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    Stay,
+    /// One-way infinite tape, the head stays on the first cell and the machine crashes
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    Crash,
+}
+
+/// Cells of a tape from its first symbol to its last, with its head relative to the first. Tapes
+/// differing only in the empty cells around their symbols have the same canonical form, wherever
+/// their heads are as long as they are at the same place relative to the symbols.
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
+pub struct Canonical<T: Clone> {
+    cells: Vec<TapeValue<T>>,
+    head: isize,
+}
+
+#[cfg(test)]
+impl<T: Clone> Canonical<T> {
+    #[must_use]
+    pub fn cells(&self) -> &[TapeValue<T>] {
+        &self.cells
+    }
+
+    /// Head position relative to the first symbol, 0 if the tape is blank
+    #[must_use]
+    pub const fn head(&self) -> isize {
+        self.head
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Tape<T: Clone> {
     head: usize,
     data: Vec<TapeValue<T>>,
     steps_ran: Option<Rc<RefCell<u64>>>,
+    left_end: LeftEnd,
+    crashed: bool,
+    end_markers: bool,
+    violated_bound: bool,
+    usage: Usage,
+    crossings: Crossings,
+}
+
+/// Tape with a single empty cell under the head
+impl<T: Clone> Default for Tape<T> {
+    fn default() -> Self {
+        Self {
+            head: 0,
+            data: vec![TapeValue::Empty],
+            steps_ran: None,
+            left_end: LeftEnd::Grow,
+            crashed: false,
+            end_markers: false,
+            violated_bound: false,
+            usage: Usage::new(0),
+            crossings: Crossings::new(0),
+        }
+    }
 }
 
+/// Tapes are equal if they hold the same cells with the head in the same place and halt the same
+/// way, however many steps they counted and however they used their cells to get there
+impl<T: Clone + PartialEq> PartialEq for Tape<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.head == other.head
+            && self.data == other.data
+            && self.left_end == other.left_end
+            && self.crashed == other.crashed
+            && self.end_markers == other.end_markers
+            && self.violated_bound == other.violated_bound
+    }
+}
+
+impl<T: Clone + Eq> Eq for Tape<T> {}
+
 impl<T: Clone> Tape<T> {
     pub fn new(initial_data: Vec<TapeConstructor<T>>, steps_ran: Option<Rc<RefCell<u64>>>) -> Self {
         let mut data = Vec::new();
@@ -43,47 +143,165 @@ impl<T: Clone> Tape<T> {
                 head,
                 data,
                 steps_ran,
+                left_end: LeftEnd::Grow,
+                crashed: false,
+                end_markers: false,
+                violated_bound: false,
+                usage: Usage::new(head),
+                crossings: Crossings::new(head),
             }
         } else {
             panic!("Tape must have a head");
         }
     }
 
-    fn increment_steps_ran(&self) {
+    /// Sets what moving left off the first cell does, two-way infinite by default
+    #[must_use]
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub const fn with_left_end(mut self, left_end: LeftEnd) -> Self {
+        self.left_end = left_end;
+        self
+    }
+
+    pub const fn left_end(&self) -> LeftEnd {
+        self.left_end
+    }
+
+    /// Whether the head has moved left off the first cell of a tape that crashes there
+    pub const fn has_crashed(&self) -> bool {
+        self.crashed
+    }
+
+    /// Bounds the tape by end markers around its current cells, as for a linear-bounded
+    /// automaton. Moving left off the first cell or right off the last is then a bound violation
+    /// and leaves the head where it is, whatever `left_end` is.
+    #[must_use]
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub const fn with_end_markers(mut self) -> Self {
+        self.end_markers = true;
+        self
+    }
+
+    pub const fn has_end_markers(&self) -> bool {
+        self.end_markers
+    }
+
+    /// Whether the head has tried to move past an end marker, see `with_end_markers`
+    pub const fn has_violated_bound(&self) -> bool {
+        self.violated_bound
+    }
+
+    /// Whether the head has crashed or violated a bound, either of which halts a run
+    pub const fn has_halted(&self) -> bool {
+        self.crashed || self.violated_bound
+    }
+
+    /// Marks the tape as a run that crashed or violated a bound left it, for restoring a
+    /// serialized tape
+    #[cfg(feature = "serde")]
+    #[must_use]
+    pub const fn with_halted(mut self, crashed: bool, violated_bound: bool) -> Self {
+        self.crashed = crashed;
+        self.violated_bound = violated_bound;
+        self
+    }
+
+    /// Cells used so far, see `Usage`. Moves and writes are tracked without counting any steps.
+    #[must_use]
+    pub fn space(&self) -> Space {
+        self.usage.space()
+    }
+
+    /// Records the machine and state each time the head crosses a boundary between cells, see
+    /// `crossing_sequences`
+    #[must_use]
+    pub fn with_crossings(mut self) -> Self {
+        self.crossings.record();
+        self
+    }
+
+    /// Crossing sequence of each boundary crossed since `with_crossings`, see `Crossings`
+    #[must_use]
+    pub fn crossing_sequences(&self) -> BTreeMap<isize, Vec<Crossing>> {
+        self.crossings.sequences()
+    }
+
+    /// Times the head has moved the other way to its last move
+    #[must_use]
+    pub const fn reversals(&self) -> u64 {
+        self.crossings.reversals()
+    }
+
+    /// Adds the cost of `operation` under the current cost model
+    fn increment_steps_ran(&self, operation: Operation) {
         if let Some(steps_ran) = self.steps_ran.as_ref() {
-            *steps_ran.borrow_mut() += 1;
+            *steps_ran.borrow_mut() += cost::operation(operation);
         }
     }
 
+    /// Shared step counter, for tapes that should count towards the same total
+    #[must_use]
+    pub fn steps_ran_counter(&self) -> Option<Rc<RefCell<u64>>> {
+        self.steps_ran.clone()
+    }
+
     // pub fn steps_ran(&self) -> u64 {
     //     *self.steps_ran.as_ref().expect("Tape must have a steps_ran").borrow()
     // }
 
-    pub fn is_at_head(&self) -> bool {
+    pub const fn is_at_head(&self) -> bool {
         self.head == 0
     }
 
     pub fn left(&mut self) {
-        self.increment_steps_ran();
+        self.increment_steps_ran(Operation::Move);
 
         if !self.is_at_head() {
             self.head -= 1;
+            self.crossings.cross(self.head + 1, Move::Left);
+        } else if self.end_markers {
+            self.violated_bound = true;
         } else {
-            self.data.insert(0, TapeValue::Empty);
+            match self.left_end {
+                LeftEnd::Grow => {
+                    self.data.insert(0, TapeValue::Empty);
+                    self.usage.grown_left();
+                    self.crossings.grown_left();
+                    self.crossings.cross(1, Move::Left);
+                }
+                LeftEnd::Stay => {}
+                LeftEnd::Crash => self.crashed = true,
+            }
         }
+        self.usage.visit(self.head);
     }
 
-    pub fn is_at_end(&self) -> bool {
+    pub const fn is_at_end(&self) -> bool {
         self.head == self.data.len() - 1
     }
 
     pub fn right(&mut self) {
-        self.increment_steps_ran();
+        self.increment_steps_ran(Operation::Move);
 
         if self.is_at_end() {
+            if self.end_markers {
+                self.violated_bound = true;
+                return;
+            }
             self.data.push(TapeValue::Empty);
         }
         self.head += 1;
+        self.crossings.cross(self.head, Move::Right);
+        self.usage.visit(self.head);
+    }
+
+    /// Staying is not a step
+    pub fn move_head(&mut self, direction: Move) {
+        match direction {
+            Move::Left => self.left(),
+            Move::Right => self.right(),
+            Move::Stay => {}
+        }
     }
 
     // #[must_use]
@@ -93,15 +311,25 @@ impl<T: Clone> Tape<T> {
 
     #[must_use]
     pub fn read(&self) -> TapeValue<T> {
-        self.increment_steps_ran();
+        self.increment_steps_ran(Operation::Read);
 
         self.data[self.head].clone()
     }
 
+    /// Counts the steps of moving right and reading `cells` times, as walking a copy of the tape
+    /// would, without moving the head or using any cells
+    pub fn count_walk(&self, cells: usize) {
+        for _ in 0..cells {
+            self.increment_steps_ran(Operation::Move);
+            self.increment_steps_ran(Operation::Read);
+        }
+    }
+
     pub fn write(&mut self, value: TapeValue<T>) {
-        self.increment_steps_ran();
+        self.increment_steps_ran(Operation::Write);
 
         self.data[self.head] = value;
+        self.usage.write(self.head);
     }
 
     #[must_use]
@@ -110,11 +338,35 @@ impl<T: Clone> Tape<T> {
     }
 
     #[must_use]
-    pub fn head(&self) -> usize {
+    pub const fn head(&self) -> usize {
         self.head
     }
 
+    /// Canonical form of the cells and head, without counting steps, see `Canonical`
     #[must_use]
+    #[allow(clippy::cast_possible_wrap)]
+    pub fn canonical(&self) -> Canonical<T>
+    where
+        T: PartialEq,
+    {
+        let symbol = |cell: &&TapeValue<T>| *cell != &TapeValue::Empty;
+        match (
+            self.data.iter().position(|cell| symbol(&cell)),
+            self.data.iter().rposition(|cell| symbol(&cell)),
+        ) {
+            (Some(first), Some(last)) => Canonical {
+                cells: self.data[first..=last].to_vec(),
+                head: self.head as isize - first as isize,
+            },
+            _ => Canonical {
+                cells: vec![],
+                head: 0,
+            },
+        }
+    }
+
+    #[must_use]
+    #[cfg(all(test, feature = "serde"))]
     pub fn as_constructor(&self) -> Vec<TapeConstructor<T>> {
         let mut tape_constructors = Vec::new();
         for (index, value) in self.data.iter().enumerate() {
@@ -186,6 +438,117 @@ mod tests {
         assert_eq!(*steps_ran.borrow(), 13);
     }
 
+    #[test]
+    fn left_end() {
+        let data = vec![
+            TapeConstructor::Head(TapeValue::Value(0)),
+            TapeConstructor::Value(TapeValue::Value(1)),
+        ];
+
+        let mut tape = Tape::new(data.clone(), None);
+        tape.left();
+        assert_eq!(tape.read(), TapeValue::Empty);
+        assert_eq!(tape.data.len(), 3);
+
+        let mut tape = Tape::new(data.clone(), None).with_left_end(LeftEnd::Stay);
+        tape.left();
+        assert_eq!(tape.read(), TapeValue::Value(0));
+        assert_eq!(tape.data.len(), 2);
+        assert!(!tape.has_crashed());
+
+        let mut tape = Tape::new(data, None).with_left_end(LeftEnd::Crash);
+        tape.right();
+        tape.left();
+        assert!(!tape.has_crashed());
+        tape.left();
+        assert!(tape.has_crashed());
+        assert_eq!(tape.read(), TapeValue::Value(0));
+    }
+
+    #[test]
+    fn end_markers() {
+        let mut tape = Tape::new(
+            vec![
+                TapeConstructor::Head(TapeValue::Value(0)),
+                TapeConstructor::Value(TapeValue::Value(1)),
+            ],
+            None,
+        )
+        .with_end_markers();
+        tape.right();
+        assert!(!tape.has_violated_bound());
+        tape.right();
+        assert!(tape.has_violated_bound());
+        assert_eq!(tape.read(), TapeValue::Value(1));
+        assert_eq!(tape.data.len(), 2);
+
+        let mut tape = Tape::<()>::default().with_end_markers();
+        tape.left();
+        assert!(tape.has_violated_bound());
+        assert_eq!(tape.data.len(), 1);
+    }
+
+    #[test]
+    fn canonical() {
+        let mut tape = Tape::new(
+            vec![
+                TapeConstructor::Head(TapeValue::Empty),
+                TapeConstructor::Value(TapeValue::Value(0)),
+                TapeConstructor::Value(TapeValue::Value(1)),
+            ],
+            None,
+        );
+        let canonical = tape.canonical();
+        assert_eq!(
+            canonical.cells(),
+            [TapeValue::Value(0), TapeValue::Value(1)]
+        );
+        assert_eq!(canonical.head(), -1);
+
+        // Padding on either side is ignored, but not where the head is
+        tape.left();
+        tape.right();
+        for _ in 0..4 {
+            tape.right();
+        }
+        for _ in 0..4 {
+            tape.left();
+        }
+        assert_ne!(tape.data().len(), 3);
+        assert_eq!(tape.canonical(), canonical);
+        tape.right();
+        assert_ne!(tape.canonical(), canonical);
+
+        assert_eq!(Tape::<()>::default().canonical(), {
+            let mut blank = Tape::<()>::default();
+            blank.right();
+            blank.canonical()
+        });
+    }
+
+    #[test]
+    fn equality() {
+        // Steps, space and crossings are left out, cells, head and halting are not
+        let data = vec![
+            TapeConstructor::Head(TapeValue::Value(0)),
+            TapeConstructor::Value(TapeValue::Value(1)),
+        ];
+        let tape = Tape::new(data.clone(), None).with_left_end(LeftEnd::Crash);
+        let mut other = Tape::new(data, Some(Rc::new(RefCell::new(0))))
+            .with_left_end(LeftEnd::Crash)
+            .with_crossings();
+        other.write(TapeValue::Value(0));
+        other.right();
+        other.left();
+        assert_eq!(other, tape);
+
+        other.left();
+        assert!(other.has_crashed());
+        assert_eq!(other.head(), tape.head());
+        assert_ne!(other, tape);
+        assert_ne!(tape.clone().with_end_markers(), tape);
+    }
+
     #[test]
     #[should_panic(expected = "Tape must have a head")]
     fn new_empty() {
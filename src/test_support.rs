@@ -0,0 +1,108 @@
+//! Alphabets and words shared by tests
+
+use std::{
+    fmt::{self, Display},
+    ops::Range,
+};
+
+use crate::alphabet::{Alphabet, WordAlphabet};
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Bit {
+    Zero,
+    One,
+}
+
+impl Alphabet for Bit {
+    fn symbols() -> &'static [Self] {
+        &[Self::Zero, Self::One]
+    }
+
+    fn to_char(&self) -> char {
+        match self {
+            Self::Zero => '0',
+            Self::One => '1',
+        }
+    }
+}
+
+impl Display for Bit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Base {
+    A,
+    C,
+    G,
+    T,
+}
+
+impl Alphabet for Base {
+    fn symbols() -> &'static [Self] {
+        &[Self::A, Self::C, Self::G, Self::T]
+    }
+
+    fn to_char(&self) -> char {
+        match self {
+            Self::A => 'a',
+            Self::C => 'c',
+            Self::G => 'g',
+            Self::T => 't',
+        }
+    }
+}
+
+/// Bases separated by hashes
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Strand {
+    Base(Base),
+    Hash,
+}
+
+impl Alphabet for Strand {
+    fn symbols() -> &'static [Self] {
+        &[
+            Self::Base(Base::A),
+            Self::Base(Base::C),
+            Self::Base(Base::G),
+            Self::Base(Base::T),
+            Self::Hash,
+        ]
+    }
+
+    fn to_char(&self) -> char {
+        match self {
+            Self::Base(base) => base.to_char(),
+            Self::Hash => '#',
+        }
+    }
+
+    fn is_separator(&self) -> bool {
+        matches!(self, Self::Hash)
+    }
+}
+
+impl WordAlphabet for Strand {
+    type Letter = Base;
+}
+
+/// Every word over `letters` of each length in `lengths`
+pub fn words(letters: &str, lengths: Range<usize>) -> Vec<String> {
+    lengths
+        .flat_map(|length| {
+            (0..length).fold(vec![String::new()], |words, _| {
+                words
+                    .iter()
+                    .flat_map(|word| {
+                        letters
+                            .chars()
+                            .map(move |letter| format!("{}{}", word, letter))
+                    })
+                    .collect()
+            })
+        })
+        .collect()
+}
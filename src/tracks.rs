@@ -0,0 +1,201 @@
+use std::{
+    fmt::{self, Debug, Display},
+    hash::Hash,
+};
+
+use crate::{
+    alphabet::{Alphabet, EMPTY_CHAR},
+    tape::{Tape, TapeValue},
+};
+
+/// Symbol holding one cell on each of several tracks. Every alphabet is a single track, and a
+/// tuple of cells over alphabets is one track per alphabet.
+pub trait Tracks: Clone + PartialEq + Eq + Hash + Debug {
+    const COUNT: usize;
+
+    /// Character on each track, in order
+    fn track_chars(&self) -> Vec<char>;
+}
+
+impl<T: Alphabet> Tracks for T {
+    const COUNT: usize = 1;
+
+    fn track_chars(&self) -> Vec<char> {
+        vec![self.to_char()]
+    }
+}
+
+/// Track `I` of a multi-track symbol, whose default has every track empty
+pub trait Track<const I: usize>: Tracks + Default {
+    type Symbol: Alphabet;
+
+    fn track(&self) -> &TapeValue<Self::Symbol>;
+    fn track_mut(&mut self) -> &mut TapeValue<Self::Symbol>;
+}
+
+fn cell_char<T: Alphabet>(cell: &TapeValue<T>) -> char {
+    match cell {
+        TapeValue::Empty => EMPTY_CHAR,
+        TapeValue::Value(symbol) => symbol.to_char(),
+    }
+}
+
+macro_rules! tracks {
+    ($count:literal; $($alphabet:ident $index:tt),+) => {
+        impl<$($alphabet: Alphabet),+> Tracks for ($(TapeValue<$alphabet>,)+) {
+            const COUNT: usize = $count;
+
+            fn track_chars(&self) -> Vec<char> {
+                vec![$(cell_char(&self.$index)),+]
+            }
+        }
+
+        tracks!(@track ($($alphabet),+); $($alphabet $index),+);
+    };
+    (@track $all:tt; $($alphabet:ident $index:tt),+) => {
+        $(tracks!(@one $all; $alphabet $index);)+
+    };
+    (@one ($($all:ident),+); $alphabet:ident $index:tt) => {
+        impl<$($all: Alphabet),+> Track<$index> for ($(TapeValue<$all>,)+) {
+            type Symbol = $alphabet;
+
+            fn track(&self) -> &TapeValue<$alphabet> {
+                &self.$index
+            }
+
+            fn track_mut(&mut self) -> &mut TapeValue<$alphabet> {
+                &mut self.$index
+            }
+        }
+    };
+}
+
+tracks!(2; A 0, B 1);
+tracks!(3; A 0, B 1, C 2);
+tracks!(4; A 0, B 1, C 2, D 3);
+
+/// Single symbol for marking cells, such as those visited or under a head
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Mark;
+
+impl Alphabet for Mark {
+    fn symbols() -> &'static [Self] {
+        &[Self]
+    }
+
+    fn to_char(&self) -> char {
+        '^'
+    }
+}
+
+#[cfg(test)]
+impl<T: Tracks> Tape<T> {
+    /// Track `I` of the cell under the head, one step like `read`
+    #[must_use]
+    pub fn read_track<const I: usize>(&self) -> TapeValue<<T as Track<I>>::Symbol>
+    where
+        T: Track<I>,
+    {
+        match self.read() {
+            TapeValue::Empty => TapeValue::Empty,
+            TapeValue::Value(cell) => cell.track().clone(),
+        }
+    }
+
+    /// Writes track `I` of the cell under the head, leaving the other tracks as they are, one step
+    /// like `write`. A cell left with every track empty is still a symbol, not an empty cell, so
+    /// written cells never end the used part of the tape.
+    pub fn write_track<const I: usize>(&mut self, value: TapeValue<<T as Track<I>>::Symbol>)
+    where
+        T: Track<I>,
+    {
+        let mut cell = match &self.data()[self.head()] {
+            TapeValue::Empty => T::default(),
+            TapeValue::Value(cell) => cell.clone(),
+        };
+        *cell.track_mut() = value;
+        self.write(TapeValue::Value(cell));
+    }
+}
+
+/// One row per track, then a row marking the head. End markers are shown as brackets.
+impl<T: Tracks> Display for Tape<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns: Vec<_> = self
+            .data()
+            .iter()
+            .map(|cell| match cell {
+                TapeValue::Empty => vec![EMPTY_CHAR; T::COUNT],
+                TapeValue::Value(cell) => cell.track_chars(),
+            })
+            .collect();
+        let (left, right) = if self.has_end_markers() {
+            ("[", "]")
+        } else {
+            ("", "")
+        };
+        for track in 0..T::COUNT {
+            let row: String = columns.iter().map(|column| column[track]).collect();
+            writeln!(f, "{}{}{}", left, row, right)?;
+        }
+        write!(f, "{}{}^", " ".repeat(left.len()), " ".repeat(self.head()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        machine::{AuxValue, MainValue},
+        tape::TapeConstructor,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn tracks() {
+        let steps_ran = Rc::new(RefCell::new(0));
+        let mut tape: Tape<(TapeValue<MainValue>, TapeValue<Mark>)> = Tape::new(
+            vec![
+                TapeConstructor::Head(TapeValue::Empty),
+                TapeConstructor::Value(TapeValue::Value((
+                    TapeValue::Value(MainValue::A),
+                    TapeValue::Empty,
+                ))),
+            ],
+            Some(Rc::clone(&steps_ran)),
+        );
+
+        tape.write_track::<1>(TapeValue::Value(Mark));
+        assert_eq!(tape.read_track::<0>(), TapeValue::Empty);
+        assert_eq!(tape.read_track::<1>(), TapeValue::Value(Mark));
+        tape.right();
+        tape.write_track::<1>(TapeValue::Value(Mark));
+        tape.write_track::<0>(TapeValue::Value(MainValue::Hash));
+        assert_eq!(tape.read_track::<0>(), TapeValue::Value(MainValue::Hash));
+        tape.right();
+        assert_eq!(tape.read_track::<0>(), TapeValue::Empty);
+        assert_eq!(tape.to_string(), "_#_\n^^_\n  ^");
+
+        // Clearing every track leaves a blank symbol rather than an empty cell
+        tape.left();
+        tape.left();
+        tape.write_track::<1>(TapeValue::Empty);
+        assert_eq!(tape.read(), TapeValue::Value(Default::default()));
+        // Each track read or write is a single step
+        assert_eq!(*steps_ran.borrow(), 13);
+    }
+
+    #[test]
+    fn single_track() {
+        let tape: Tape<AuxValue> = Tape::new(
+            vec![
+                TapeConstructor::Value(TapeValue::Value(AuxValue::A)),
+                TapeConstructor::Head(TapeValue::Value(AuxValue::B)),
+                TapeConstructor::Value(TapeValue::Empty),
+            ],
+            None,
+        );
+        assert_eq!(tape.to_string(), "ab_\n ^");
+        assert_eq!(tape.with_end_markers().to_string(), "[ab_]\n  ^");
+    }
+}
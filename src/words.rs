@@ -0,0 +1,179 @@
+use std::fmt::{self, Display};
+
+use crate::{
+    alphabet::{Alphabet, WordAlphabet},
+    tape::{Tape, TapeValue},
+};
+
+/// Symbols in consecutive cells of a tape, read without counting any steps
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Word<'a, T: Alphabet> {
+    start: usize,
+    cells: &'a [TapeValue<T>],
+}
+
+impl<'a, T: Alphabet> Word<'a, T> {
+    /// Index of the first cell
+    #[cfg(test)]
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Index of the cell after the last
+    #[cfg(test)]
+    pub const fn end(&self) -> usize {
+        self.start + self.cells.len()
+    }
+
+    pub const fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    #[cfg(test)]
+    pub const fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn symbols(&self) -> Vec<T> {
+        self.cells
+            .iter()
+            .map(|cell| match cell {
+                TapeValue::Value(symbol) => symbol.clone(),
+                TapeValue::Empty => unreachable!("Words must not have empty cells"),
+            })
+            .collect()
+    }
+}
+
+impl<'a, T: WordAlphabet> Word<'a, T> {
+    /// Letters of a word between separators
+    pub fn letters(&self) -> Vec<T::Letter> {
+        self.symbols()
+            .iter()
+            .map(|symbol| symbol.letter().expect("Words must not have separators"))
+            .collect()
+    }
+}
+
+impl<'a, T: Alphabet> Display for Word<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.cells.iter().try_for_each(|cell| write!(f, "{}", cell))
+    }
+}
+
+/// Words separated by separators up to the first empty cell, see `Tape::words_from`
+pub struct Words<'a, T: WordAlphabet> {
+    start: usize,
+    cells: Option<&'a [TapeValue<T>]>,
+}
+
+impl<'a, T: WordAlphabet> Iterator for Words<'a, T> {
+    type Item = Word<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cells = self.cells?;
+        let length = cells
+            .iter()
+            .position(|cell| !matches!(cell, TapeValue::Value(symbol) if symbol.letter().is_some()))
+            .unwrap_or(cells.len());
+        let word = Word {
+            start: self.start,
+            cells: &cells[..length],
+        };
+        self.cells = match cells.get(length) {
+            Some(TapeValue::Value(_)) => Some(&cells[length + 1..]),
+            Some(TapeValue::Empty) | None => None,
+        };
+        self.start += length + 1;
+        Some(word)
+    }
+}
+
+impl<T: Alphabet> Tape<T> {
+    /// Cells from `index` to the last, without counting any steps
+    pub fn cells_from(&self, index: usize) -> impl Iterator<Item = &TapeValue<T>> {
+        self.data().get(index..).unwrap_or_default().iter()
+    }
+
+    /// Symbols from `index` up to the first empty cell, without counting any steps
+    pub fn word_at(&self, index: usize) -> Word<'_, T> {
+        let cells = self.data().get(index..).unwrap_or_default();
+        let length = cells
+            .iter()
+            .position(|cell| cell == &TapeValue::Empty)
+            .unwrap_or(cells.len());
+        Word {
+            start: index,
+            cells: &cells[..length],
+        }
+    }
+
+    /// Words from `index` up to the first empty cell, split on separators, without counting any
+    /// steps. An empty cell at `index` has no words, while adjacent separators have an empty word
+    /// between them.
+    pub fn words_from(&self, index: usize) -> Words<'_, T>
+    where
+        T: WordAlphabet,
+    {
+        let cells = self.data().get(index..).unwrap_or_default();
+        Words {
+            start: index,
+            cells: cells
+                .first()
+                .is_some_and(|cell| cell != &TapeValue::Empty)
+                .then_some(cells),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::TapeBuilder,
+        machine::{AuxValue, MainValue},
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn words() {
+        let steps_ran = Rc::new(RefCell::new(0));
+        let tape: Tape<MainValue> = TapeBuilder::new()
+            .steps_ran(Some(Rc::clone(&steps_ran)))
+            .head()
+            .blank()
+            .words(&["ab", "", "b", ""])
+            .blank()
+            .word("a")
+            .build();
+        let words: Vec<_> = tape.words_from(1).map(|word| word.to_string()).collect();
+        assert_eq!(words, ["ab", "", "b", ""]);
+        let word = tape.words_from(1).nth(2).unwrap();
+        assert_eq!((word.start(), word.end()), (5, 6));
+        assert_eq!(word.symbols(), [MainValue::B]);
+        assert_eq!(word.letters(), [AuxValue::B]);
+        assert_eq!(tape.words_from(0).count(), 0);
+        assert_eq!(tape.words_from(9).count(), 0);
+        assert_eq!(tape.cells_from(7).count(), 2);
+        assert_eq!(*steps_ran.borrow(), 0);
+    }
+
+    #[test]
+    fn word_at() {
+        let steps_ran = Rc::new(RefCell::new(0));
+        let tape: Tape<AuxValue> = TapeBuilder::new()
+            .steps_ran(Some(Rc::clone(&steps_ran)))
+            .head()
+            .blank()
+            .word("ab")
+            .blank()
+            .word("a")
+            .build();
+        assert_eq!(tape.word_at(1).to_string(), "ab");
+        assert_eq!(tape.word_at(1).symbols(), [AuxValue::A, AuxValue::B]);
+        assert!(tape.word_at(0).is_empty());
+        assert_eq!(tape.word_at(4).len(), 1);
+        assert!(tape.word_at(5).is_empty());
+        assert_eq!(*steps_ran.borrow(), 0);
+    }
+}